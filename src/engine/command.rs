@@ -3,10 +3,12 @@ use strum::EnumIter;
 use sculpt_macro::{EnumDoc, EnumType};
 use crate::{logic::rule::{Rule, RuleType, Side}, error::Error};
 use crate::logic::{Formula, Term};
-use crate::syntax::lexer::{Lexer, Token};
+use crate::syntax::lexer::{Lexer, Spanned, Token};
+use crate::syntax::parser;
+use crate::tactics;
 
 
-const DEFAULT_RULES: [&str; 16] = [
+const DEFAULT_RULES: [&str; 21] = [
     "axiom",
     "intro",
     "intros",
@@ -17,18 +19,68 @@ const DEFAULT_RULES: [&str; 16] = [
     "keep_left",
     "keep_right",
     "from_or",
+    "have",
     "gen",
     "fix_as",
     "consider",
     "rename_as",
+    "clear",
+    "rename",
+    "move",
+    "duplicate",
     "from_bottom",
     "exfalso"
 ];
 
+const GOAL_COMMANDS: [&str; 4] = [
+    "focus",
+    "swap",
+    "rotate",
+    "defer"
+];
+
+const TACTICS: [&str; 7] = [
+    "apply",
+    "exact",
+    "assumption",
+    "contradiction",
+    "tauto",
+    "auto",
+    "firstorder"
+];
+
+const COMBINATORS: [&str; 4] = [
+    "try",
+    "repeat",
+    "first",
+    "all"
+];
+
+const HISTORY_COMMANDS: [&str; 2] = [
+    "undo",
+    "redo"
+];
+
 fn is_rule(str: &str) -> bool {
     DEFAULT_RULES.contains(&str)
 }
 
+fn is_goal_command(str: &str) -> bool {
+    GOAL_COMMANDS.contains(&str)
+}
+
+fn is_tactic(str: &str) -> bool {
+    TACTICS.contains(&str)
+}
+
+fn is_combinator(str: &str) -> bool {
+    COMBINATORS.contains(&str)
+}
+
+fn is_history_command(str: &str) -> bool {
+    HISTORY_COMMANDS.contains(&str)
+}
+
 
 /// Control command for the engine. Those rules are not directly linked to natural deduction.
 #[derive(Clone, Debug, EnumIter, EnumDoc, EnumType, PartialEq)]
@@ -39,12 +91,101 @@ pub enum ContextCommand {
         desc = "Create a new theorem and start the proof mode"
     )]
     Theorem(String, Box<Formula>),
-    #[cmd(name = "Use", usage = "<thm_name>", desc = "Adds a theorem to the proof context")]
-    Use(String),
+    #[cmd(name = "Axiom", usage = "<name> :: <F>", desc = "Add F to the context as a theorem, without proof")]
+    Axiom(String, Box<Formula>),
+    #[cmd(
+        name = "Use",
+        usage = "<thm_name> [[A := <F>, ...]] [with <t1>, ... | with <x1> := <t1>, ...]",
+        desc = "Adds a theorem to the proof context, instantiating its schematic variables with the given formulas and its leading universal quantifiers with the given terms"
+    )]
+    Use(String, Vec<(String, Formula)>, Vec<(Option<String>, Term)>),
+    #[cmd(
+        name = "apply",
+        usage = "<thm_name>",
+        desc = "Unify the conclusion of a theorem with the goal, and replace the goal with the premises of the theorem"
+    )]
+    Apply(String),
+    #[cmd(
+        name = "exact",
+        usage = "<thm_name>",
+        desc = "Prove the goal with a theorem, instantiating it by unification"
+    )]
+    Exact(String),
+    #[cmd(
+        name = "assumption",
+        desc = "Prove the goal with an antecedent, up to the renaming of bound variables, or with a theorem"
+    )]
+    Assumption,
+    #[cmd(
+        name = "contradiction",
+        desc = "Prove the goal when falsum, or both F and ~F, are in the antecedents"
+    )]
+    Contradiction,
+    #[cmd(
+        name = "tauto",
+        usage = "[classical]",
+        desc = "Prove the goal if it is a propositional tautology, intuitionistic or classical"
+    )]
+    Tauto(bool),
+    #[cmd(
+        name = "auto",
+        usage = "[depth]",
+        desc = "Search a proof of the goal at most depth rules high (default: 5), not counting intro, and record its steps"
+    )]
+    Auto(usize),
+    #[cmd(
+        name = "firstorder",
+        usage = "[bound]",
+        desc = "Prove the goal in first-order logic, with at most bound instances (default: 3) of each universal formula on a branch"
+    )]
+    FirstOrder(usize),
+    #[cmd(
+        name = "<t1>; <t2>",
+        desc = "Apply t2 to every goal created by t1. Tactics also combine as 'try t', 'repeat t', 'first [t1 | t2 | ...]' and 'all: t'"
+    )]
+    Tactic(Box<Tactic>),
     #[cmd(name = "Admit", desc = "Consider the current goal proven, exit proof mode")]
     Admit,
     #[cmd(name = "Qed", desc = "Finish the proof & exit proof mode (only when no more subgoals)")]
     Qed,
+    #[cmd(
+        name = "Show Goals",
+        usage = "[compact]",
+        desc = "Display every remaining goal (compact: only the consequent of the other goals)"
+    )]
+    ShowGoals(bool),
+    #[cmd(
+        name = "Show Proof",
+        usage = "[<thm_name>]",
+        desc = "Display the derivation tree of the current proof or of a proven theorem"
+    )]
+    ShowProof(Option<String>),
+    #[cmd(
+        name = "Print Assumptions",
+        usage = "<thm_name>",
+        desc = "List the axioms and admitted theorems the theorem depends on"
+    )]
+    PrintAssumptions(String),
+    #[cmd(
+        name = "Search",
+        usage = "<pattern>",
+        desc = "List the theorems whose conclusion matches the pattern, where '_', '?x' and '?A' are holes"
+    )]
+    Search(Box<Formula>),
+    #[cmd(name = "focus", usage = "<n>", desc = "Make the n-th goal the current goal")]
+    Focus(usize),
+    #[cmd(name = "swap", desc = "Exchange the first two goals")]
+    Swap,
+    #[cmd(name = "rotate", usage = "[n]", desc = "Move the first n goals (default: 1) to the end of the goal list")]
+    Rotate(usize),
+    #[cmd(name = "defer", usage = "[n]", desc = "Move the n-th goal (default: current) to the end of the goal list")]
+    Defer(usize),
+    #[cmd(name = "undo", usage = "[n]", desc = "Revert the last n commands (default: 1)")]
+    Undo(usize),
+    #[cmd(name = "redo", desc = "Apply again the last reverted command")]
+    Redo,
+    #[cmd(name = "Reset", usage = "<thm_name>", desc = "Revert every command since the statement of the theorem, included")]
+    Reset(String),
 }
 
 impl Display for ContextCommand {
@@ -52,7 +193,44 @@ impl Display for ContextCommand {
         match self {
             //InterpreterCommand::Context(s) => write!(f, "context {s}"),
             ContextCommand::Theorem(name, formula) => write!(f, "Thm {name} :: {formula}"),
+            ContextCommand::Axiom(name, formula) => write!(f, "Axiom {name} :: {formula}"),
+            ContextCommand::Use(name, schema, instances) => {
+                write!(f, "Use {name}")?;
+                if !schema.is_empty() {
+                    let schema = schema.iter()
+                        .map(|(m, g)| format!("{} := {g}", m.trim_start_matches('?')))
+                        .collect::<Vec<_>>();
+                    write!(f, " [{}]", schema.join(", "))?;
+                }
+                if !instances.is_empty() {
+                    let instances = instances.iter()
+                        .map(|(v, t)| match v {
+                            Some(v) => format!("{v} := {t}"),
+                            None => t.to_string()
+                        })
+                        .collect::<Vec<_>>();
+                    write!(f, " with {}", instances.join(", "))?;
+                }
+                Ok(())
+            }
+            ContextCommand::Apply(thm) => write!(f, "apply {thm}"),
+            ContextCommand::Exact(thm) => write!(f, "exact {thm}"),
+            ContextCommand::Tauto(true) => write!(f, "tauto classical"),
+            ContextCommand::Auto(n) => write!(f, "auto {n}"),
+            ContextCommand::FirstOrder(n) => write!(f, "firstorder {n}"),
+            ContextCommand::Tactic(t) => write!(f, "{t}"),
             ContextCommand::Admit => write!(f, "admit"),
+            ContextCommand::ShowGoals(true) => write!(f, "Show Goals"),
+            ContextCommand::ShowGoals(false) => write!(f, "Show Goals compact"),
+            ContextCommand::ShowProof(None) => write!(f, "Show Proof"),
+            ContextCommand::ShowProof(Some(thm)) => write!(f, "Show Proof {thm}"),
+            ContextCommand::PrintAssumptions(thm) => write!(f, "Print Assumptions {thm}"),
+            ContextCommand::Search(pattern) => write!(f, "Search {pattern}"),
+            ContextCommand::Focus(n) => write!(f, "focus {n}"),
+            ContextCommand::Rotate(n) => write!(f, "rotate {n}"),
+            ContextCommand::Defer(n) => write!(f, "defer {n}"),
+            ContextCommand::Undo(n) => write!(f, "undo {n}"),
+            ContextCommand::Reset(thm) => write!(f, "Reset {thm}"),
             e => match e.name() {
                 Some(n) => write!(f, "{n}"),
                 None => Ok(())
@@ -85,6 +263,12 @@ pub enum RuleCommand {
     KeepRight,
     #[cmd(name = "from_or", usage = "<F> \\/ <G>")]
     FromOr(Box<Formula>),
+    #[cmd(
+        name = "have",
+        usage = "[<H> ::] <F> [by <rule>]",
+        desc = "Prove an intermediate lemma F, then use it as hypothesis H"
+    )]
+    Have(Option<String>, Box<Formula>, Option<Box<RuleCommand>>),
     #[cmd(name = "gen", usage = "<T>")]
     Generalize(Box<Term>),
     #[cmd(name = "fix_as", usage = "<T>")]
@@ -93,6 +277,14 @@ pub enum RuleCommand {
     Consider(Box<Formula>),
     #[cmd(name = "rename_as", usage = "<v>")]
     RenameAs(String),
+    #[cmd(name = "clear", usage = "<H>", desc = "Remove hypothesis H from the current goal")]
+    Clear(String),
+    #[cmd(name = "rename", usage = "<H> into <K>", desc = "Rename hypothesis H to K")]
+    Rename(String, String),
+    #[cmd(name = "move", usage = "<H> after <K>", desc = "Move hypothesis H right after hypothesis K")]
+    Move(String, String),
+    #[cmd(name = "duplicate", usage = "<H>", desc = "Add a copy of hypothesis H to the current goal")]
+    Duplicate(String),
     #[cmd(name = "from_bottom", usage = "<F>")]
     FromBottom,
     #[cmd(name = "exfalso", usage = "<F>")]
@@ -107,10 +299,23 @@ impl Display for RuleCommand {
             RuleCommand::AndLeft(s) => write!(f, "and_left {s}"),
             RuleCommand::AndRight(s) => write!(f, "and_right {s}"),
             RuleCommand::FromOr(s) => write!(f, "from_or {s}"),
+            RuleCommand::Have(n, s, by) => {
+                write!(f, "have ")?;
+                if let Some(n) = n { write!(f, "{n} :: ")?; }
+                write!(f, "{s}")?;
+                match by {
+                    Some(r) => write!(f, " by {r}"),
+                    None => Ok(())
+                }
+            }
             RuleCommand::Generalize(s) => write!(f, "gen {s}"),
             RuleCommand::FixAs(s) => write!(f, "fix_as {s}"),
             RuleCommand::Consider(s) => write!(f, "consider {s}"),
             RuleCommand::RenameAs(s) => write!(f, "rename_as {s}"),
+            RuleCommand::Clear(h) => write!(f, "clear {h}"),
+            RuleCommand::Rename(h, k) => write!(f, "rename {h} into {k}"),
+            RuleCommand::Move(h, k) => write!(f, "move {h} after {k}"),
+            RuleCommand::Duplicate(h) => write!(f, "duplicate {h}"),
             RuleCommand::ExFalso(s) => write!(f, "exfalso {s}"),
             e => match e.name() {
                 Some(n) => write!(f, "{n}"),
@@ -134,10 +339,15 @@ impl RuleCommand {
             RuleCommand::KeepLeft => (vec!["Γ ⊢ F"], "Γ ⊢ F \\/ G"),
             RuleCommand::KeepRight => (vec!["Γ ⊢ G"], "Γ ⊢ F \\/ G"),
             RuleCommand::FromOr(_) => (vec!["Γ ⊢ F \\/ G", "Γ, F ⊢ H", "Γ, G ⊢ H"], "Γ ⊢ H"),
+            RuleCommand::Have(..) => (vec!["Γ ⊢ F", "Γ, H:F ⊢ G"], "Γ ⊢ G"),
             RuleCommand::Generalize(_) => (vec!["Γ ⊢ forall v, F"], "Γ ⊢ F[v -> T]"),
             RuleCommand::FixAs(_) => (vec!["Γ ⊢ F[v -> T]"], "Γ ⊢ exists v, F"),
             RuleCommand::Consider(_) => (vec!["Γ ⊢ exists v, F", "Γ, F ⊢ G"], "Γ ⊢ G"),
             RuleCommand::RenameAs(_) => (vec!["Γ ⊢ forall/exists v, F[x -> v]"], "Γ ⊢ forall/exists x, F"),
+            RuleCommand::Clear(_) => (vec!["Γ ⊢ G"], "Γ, H:F ⊢ G"),
+            RuleCommand::Rename(..) => (vec!["Γ, K:F ⊢ G"], "Γ, H:F ⊢ G"),
+            RuleCommand::Move(..) => (vec!["Γ, K:F, H:E, Δ ⊢ G"], "Γ, H:E, K:F, Δ ⊢ G"),
+            RuleCommand::Duplicate(_) => (vec!["Γ, H:F, H':F ⊢ G"], "Γ, H:F ⊢ G"),
             RuleCommand::FromBottom => (vec!["Γ, ~F ⊢ falsum"], "Γ ⊢ F"),
             RuleCommand::ExFalso(_) => (vec!["Γ ⊢ F", "Γ ⊢ ~F"], "Γ ⊢ falsum"),
 
@@ -160,10 +370,15 @@ impl RuleCommand {
             RuleCommand::KeepLeft => Rule::Keep(Side::Left),
             RuleCommand::KeepRight => Rule::Keep(Side::Right),
             RuleCommand::FromOr(s) => Rule::FromOr(s.clone()),
+            RuleCommand::Have(n, s, _) => Rule::Cut(n.clone(), s.clone()),
             RuleCommand::Generalize(s) => Rule::Generalize(s.clone()),
             RuleCommand::FixAs(s) => Rule::FixAs(s.clone()),
             RuleCommand::Consider(s) => Rule::Consider(s.clone()),
             RuleCommand::RenameAs(s) => Rule::RenameAs(s.clone()),
+            RuleCommand::Clear(h) => Rule::Clear(h.clone()),
+            RuleCommand::Rename(h, k) => Rule::RenameHypothesis(h.clone(), k.clone()),
+            RuleCommand::Move(h, k) => Rule::MoveAfter(h.clone(), k.clone()),
+            RuleCommand::Duplicate(h) => Rule::Duplicate(h.clone()),
             RuleCommand::FromBottom => Rule::FromBottom,
             RuleCommand::ExFalso(s) => Rule::ExFalso(s.clone())
         }
@@ -183,10 +398,16 @@ impl RuleCommandType {
             RuleType::And => vec![RuleCommandType::AndRight, RuleCommandType::AndLeft],
             RuleType::Keep => vec![RuleCommandType::KeepRight, RuleCommandType::KeepLeft],
             RuleType::FromOr => vec![RuleCommandType::FromOr],
+            RuleType::Cut => vec![RuleCommandType::Have],
+            RuleType::Use => vec![],
             RuleType::Generalize => vec![RuleCommandType::Generalize],
             RuleType::FixAs => vec![RuleCommandType::FixAs],
             RuleType::Consider => vec![RuleCommandType::Consider],
             RuleType::RenameAs => vec![RuleCommandType::RenameAs],
+            RuleType::Clear => vec![RuleCommandType::Clear],
+            RuleType::RenameHypothesis => vec![RuleCommandType::Rename],
+            RuleType::MoveAfter => vec![RuleCommandType::Move],
+            RuleType::Duplicate => vec![RuleCommandType::Duplicate],
             RuleType::FromBottom => vec![RuleCommandType::FromBottom],
             RuleType::ExFalso => vec![RuleCommandType::ExFalso],
         }
@@ -210,12 +431,222 @@ impl Display for EngineCommand {
 }
 
 
+/// Tactic expression, combining the commands applied to the goals of a proof.
+/// The goals created by a tactic are the ones replacing the current goal once it is applied.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tactic {
+    /// A rule or a tactic command, applied to the current goal
+    Command(Box<EngineCommand>),
+    /// `t1; t2`: apply t2 to every goal created by t1
+    Then(Box<Tactic>, Box<Tactic>),
+    /// `try t`: apply t, or leave the goal untouched if it fails
+    Try(Box<Tactic>),
+    /// `repeat t`: apply t, then again to every created goal, until it fails or makes no progress
+    Repeat(Box<Tactic>),
+    /// `first [t1 | t2 | ...]`: apply the first tactic which succeeds
+    First(Vec<Tactic>),
+    /// `all: t`: apply t to every goal of the proof
+    All(Box<Tactic>),
+}
+
+// Only needed to iterate over the variants of ContextCommand: the tactic always failing
+impl Default for Tactic {
+    fn default() -> Self {
+        Tactic::First(vec![])
+    }
+}
+
+impl Display for Tactic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Sequences are parenthesized when under a combinator
+        let operand = |t: &Tactic| match t {
+            Tactic::Then(..) | Tactic::All(_) => format!("({t})"),
+            t => t.to_string()
+        };
+
+        match self {
+            Tactic::Command(c) => write!(f, "{c}"),
+            Tactic::Then(t1, t2) => write!(f, "{}; {}", t1, operand(t2)),
+            Tactic::Try(t) => write!(f, "try {}", operand(t)),
+            Tactic::Repeat(t) => write!(f, "repeat {}", operand(t)),
+            Tactic::First(ts) => write!(f, "first [{}]", ts.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" | ")),
+            Tactic::All(t) => write!(f, "all: {t}")
+        }
+    }
+}
+
+
+impl Tactic {
+    /// Return whether the expression uses 'auto', whose steps are recorded instead of the command
+    pub fn uses_auto(&self) -> bool {
+        match self {
+            Tactic::Command(c) => matches!(c.as_ref(), EngineCommand::ContextCommand(ContextCommand::Auto(_))),
+            Tactic::Then(t1, t2) => t1.uses_auto() || t2.uses_auto(),
+            Tactic::Try(t) | Tactic::Repeat(t) | Tactic::All(t) => t.uses_auto(),
+            Tactic::First(ts) => ts.iter().any(|t| t.uses_auto())
+        }
+    }
+
+
+    /// Parse a tactic expression:
+    /// ```text
+    /// expr  := ['all' ':'] seq
+    /// seq   := unary (';' unary)*
+    /// unary := 'try' unary | 'repeat' unary | 'first' '[' seq ('|' seq)* ']' | '(' seq ')' | command
+    /// ```
+    fn parse(source: &str, tokens: &[Spanned<Token, usize>]) -> Result<Tactic, Error> {
+        let mut parser = TacticParser { source, tokens, pos: 0 };
+
+        let all = matches!(tokens, [(_, Token::Ident(s), _), (_, Token::Colon, _), ..] if s == "all");
+        if all {
+            parser.pos = 2;
+        }
+
+        let tactic = parser.parse_seq()?;
+        if let Some((_, t, _)) = parser.peek() {
+            return Err(Error::InvalidArguments(format!("Unexpected '{t}' in the tactic expression")))
+        }
+
+        Ok(if all { Tactic::All(Box::new(tactic)) } else { tactic })
+    }
+}
+
+
+struct TacticParser<'a> {
+    source: &'a str,
+    tokens: &'a [Spanned<Token, usize>],
+    pos: usize
+}
+
+impl TacticParser<'_> {
+    fn peek(&self) -> Option<&Spanned<Token, usize>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        match self.peek() {
+            Some((_, t, _)) if std::mem::discriminant(t) == std::mem::discriminant(&expected) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some((_, t, _)) => Err(Error::InvalidArguments(format!("Expected '{expected}', got '{t}'"))),
+            None => Err(Error::ArgumentsRequired(format!("Expected '{expected}'")))
+        }
+    }
+
+    fn parse_seq(&mut self) -> Result<Tactic, Error> {
+        let mut tactic = self.parse_unary()?;
+        while let Some((_, Token::Semicolon, _)) = self.peek() {
+            self.pos += 1;
+            tactic = Tactic::Then(Box::new(tactic), Box::new(self.parse_unary()?));
+        }
+        Ok(tactic)
+    }
+
+    fn parse_unary(&mut self) -> Result<Tactic, Error> {
+        let combinator = match self.peek() {
+            Some((_, Token::Ident(s), _)) if is_combinator(s) => s.clone(),
+            Some((_, Token::OpenParen, _)) => {
+                self.pos += 1;
+                let tactic = self.parse_seq()?;
+                self.expect(Token::CloseParen)?;
+                return Ok(tactic)
+            }
+            Some(_) => return self.parse_command(),
+            None => return Err(Error::ArgumentsRequired("Expected a tactic".to_string()))
+        };
+        self.pos += 1;
+
+        match combinator.as_str() {
+            "try" => Ok(Tactic::Try(Box::new(self.parse_unary()?))),
+            "repeat" => Ok(Tactic::Repeat(Box::new(self.parse_unary()?))),
+            "first" => {
+                self.expect(Token::OpenBracket)?;
+                let mut tactics = vec![self.parse_seq()?];
+                while let Some((_, Token::Bar, _)) = self.peek() {
+                    self.pos += 1;
+                    tactics.push(self.parse_seq()?);
+                }
+                self.expect(Token::CloseBracket)?;
+                Ok(Tactic::First(tactics))
+            }
+            "all" => Err(Error::InvalidArguments("'all:' can only start a tactic expression".to_string())),
+            _ => unreachable!()
+        }
+    }
+
+    /// Parse the command ending at the next combinator symbol found outside of parentheses and brackets
+    fn parse_command(&mut self) -> Result<Tactic, Error> {
+        let start = self.tokens[self.pos].0;
+        let mut depth = 0;
+
+        while let Some((_, t, _)) = self.peek() {
+            match t {
+                Token::Semicolon | Token::Bar | Token::CloseParen | Token::CloseBracket if depth == 0 => break,
+                Token::OpenParen | Token::OpenBracket => depth += 1,
+                Token::CloseParen | Token::CloseBracket => depth -= 1,
+                _ => ()
+            }
+            self.pos += 1;
+        }
+        let end = self.peek().map_or(self.source.len(), |(s, _, _)| *s);
+
+        let command = match EngineCommand::parse_command(&mut Lexer::from(&self.source[start..end]))? {
+            Some(c) => c,
+            None => return Err(Error::ArgumentsRequired("Expected a tactic".to_string()))
+        };
+
+        match &command {
+            EngineCommand::RuleCommand(_) | EngineCommand::ContextCommand(
+                ContextCommand::Use(..) | ContextCommand::Apply(_) | ContextCommand::Exact(_) | ContextCommand::Assumption
+                | ContextCommand::Contradiction | ContextCommand::Tauto(_) | ContextCommand::Auto(_) | ContextCommand::FirstOrder(_)
+            ) => Ok(Tactic::Command(Box::new(command))),
+            c => Err(Error::InvalidArguments(format!("'{c}' cannot be used in a tactic expression")))
+        }
+    }
+}
+
+
 impl EngineCommand {
+    /// Parse a command, or a tactic expression if it uses combinators
     pub fn parse(command: &mut Lexer) -> Result<Option<EngineCommand>, Error> {
-        let next = match command.next() {
-            Some(c) => {
-                c.expect("LexicalError")
+        let source = command.source();
+
+        // The tokens after an unknown one are not needed to tell if it is a tactic expression
+        let mut tokens = vec![];
+        let mut unknown = None;
+        for t in command {
+            match t {
+                Ok(t) => tokens.push(t),
+                Err(e) => {
+                    unknown = Some(e);
+                    break
+                }
             }
+        }
+
+        let is_tactic_expression = match tokens.first() {
+            Some((_, Token::Ident(s), _)) if is_combinator(s) => true,
+            Some((_, Token::OpenParen, _)) => true,
+            _ => tokens.iter().any(|(_, t, _)| matches!(t, Token::Semicolon))
+        };
+        if !is_tactic_expression {
+            return EngineCommand::parse_command(&mut Lexer::from(source))
+        }
+        if let Some(e) = unknown {
+            return Err(e.into())
+        }
+
+        match Tactic::parse(source, &tokens)? {
+            Tactic::Command(c) => Ok(Some(*c)),
+            t => Ok(Some(EngineCommand::ContextCommand(ContextCommand::Tactic(Box::new(t)))))
+        }
+    }
+
+
+    fn parse_command(command: &mut Lexer) -> Result<Option<EngineCommand>, Error> {
+        let next = match command.next() {
+            Some(c) => c?,
             None => return Ok(None) // empty command
         };
 
@@ -225,9 +656,21 @@ impl EngineCommand {
             (_, Token::Use, _) => EngineCommand::parse_use(command),
             (_, Token::Qed, _) => EngineCommand::parse_qed(command),
             (_, Token::Admit, _) => EngineCommand::parse_admit(command),
+            // Those commands are not keywords, so that their names can still be used in formulas
+            (_, Token::Ident(s), _) if s == "Axiom" => EngineCommand::parse_axiom(command),
+            (_, Token::Ident(s), _) if s == "Show" => EngineCommand::parse_show(command),
+            (_, Token::Ident(s), _) if s == "Reset" => EngineCommand::parse_reset(command),
+            (_, Token::Ident(s), _) if s == "Print" => EngineCommand::parse_print(command),
+            (_, Token::Ident(s), _) if s == "Search" => EngineCommand::parse_search(command),
             (_, Token::Ident(s), _) => {
                 if is_rule(&s) {
                     EngineCommand::parse_rule(command, s)
+                } else if is_goal_command(&s) {
+                    EngineCommand::parse_goal_command(command, s)
+                } else if is_tactic(&s) {
+                    EngineCommand::parse_tactic(command, s)
+                } else if is_history_command(&s) {
+                    EngineCommand::parse_history_command(command, s)
                 } else {
                     Err(Error::UnknownCommand(s))
                 }
@@ -246,15 +689,28 @@ impl EngineCommand {
 
 
     fn parse_thm(lxr: &mut Lexer) -> Result<EngineCommand, Error> {
+        let (name, formula) = EngineCommand::parse_statement(lxr)?;
+        Ok(EngineCommand::ContextCommand(ContextCommand::Theorem(name, Box::new(formula))))
+    }
+
+
+    fn parse_axiom(lxr: &mut Lexer) -> Result<EngineCommand, Error> {
+        let (name, formula) = EngineCommand::parse_statement(lxr)?;
+        Ok(EngineCommand::ContextCommand(ContextCommand::Axiom(name, Box::new(formula))))
+    }
+
+
+    /// Parse '<name> :: <F>'
+    fn parse_statement(lxr: &mut Lexer) -> Result<(String, Formula), Error> {
         // Next token is the theorem name
-        let thm_name = match lxr.next_token().expect("LexicalError") { // todo: use something other than UnexpectedEOF
+        let thm_name = match lxr.next_token()? { // todo: use something other than UnexpectedEOF
             Some(Token::Ident(s)) => s,
             Some(t) => return Err(Error::InvalidCommand(format!("Expected a name, got '{t}'"))),
             None => return Err(Error::UnexpectedEOF)
         };
 
         // Next is '::'
-        match lxr.next_token().expect("LexicalError") { // todo: same
+        match lxr.next_token()? { // todo: same
             Some(Token::DoubleColon) => (),
             Some(t) => return Err(Error::InvalidCommand(format!("Expected '::', got '{t}'"))),
             None => return Err(Error::UnexpectedEOF)
@@ -263,7 +719,7 @@ impl EngineCommand {
         // Next is the theorem's formula
         let formula = Formula::parse(lxr)?;
 
-        Ok(EngineCommand::ContextCommand(ContextCommand::Theorem(thm_name, Box::new(formula))))
+        Ok((thm_name, formula))
     }
 
 
@@ -273,13 +729,42 @@ impl EngineCommand {
 
 
     fn parse_use(lxr: &mut Lexer) -> Result<EngineCommand, Error> {
-        match lxr.next() {
-            None => Err(Error::ArgumentsRequired("Expected a theorem name".to_string())),
-            Some(r) => match r.expect("LexicalError") {
-                (_, Token::Ident(s), _) => Ok(EngineCommand::ContextCommand(ContextCommand::Use(s))),
-                (_, t, _) => Err(Error::InvalidArguments(format!("Expected a theorem name, got '{t}")))
+        let thm_name = match lxr.next() {
+            None => return Err(Error::ArgumentsRequired("Expected a theorem name".to_string())),
+            Some(r) => match r? {
+                (_, Token::Ident(s), _) => s,
+                (_, t, _) => return Err(Error::InvalidArguments(format!("Expected a theorem name, got '{t}")))
+            }
+        };
+
+        let mut next = lxr.next_token()?;
+
+        // Optional instantiation of the schematic variables
+        let mut schema = vec![];
+        if let Some(Token::OpenBracket) = next {
+            let (text, end) = lxr.read_until(|t| matches!(t, Token::CloseBracket))?;
+            if end.is_none() {
+                return Err(Error::InvalidArguments("Expected ']'".to_string()));
             }
+            schema = parser::SchemaInstancesParser::new().parse(Lexer::from(text))
+                .map_err(|_| Error::InvalidArguments("Expected instantiations 'A := F'".to_string()))?;
+            next = lxr.next_token()?;
         }
+
+        // Optional instantiation of the leading universal quantifiers
+        let instances = match next {
+            None => vec![],
+            Some(Token::Ident(s)) if s == "with" => {
+                if lxr.is_finished() {
+                    return Err(Error::ArgumentsRequired("Expected terms".to_string()));
+                }
+                parser::InstancesParser::new().parse(lxr)
+                    .map_err(|_| Error::InvalidArguments("Expected terms, or instantiations 'x := t'".to_string()))?
+            }
+            Some(t) => return Err(Error::InvalidArguments(format!("Expected '[' or 'with', got '{t}'")))
+        };
+
+        Ok(EngineCommand::ContextCommand(ContextCommand::Use(thm_name, schema, instances)))
     }
 
 
@@ -291,6 +776,150 @@ impl EngineCommand {
         Ok(EngineCommand::ContextCommand(ContextCommand::Admit))
     }
 
+    fn parse_show(lxr: &mut Lexer) -> Result<EngineCommand, Error> {
+        match lxr.next_token()? {
+            Some(Token::Ident(s)) if s == "Goals" => (),
+            Some(Token::Ident(s)) if s == "Proof" => {
+                let theorem = match lxr.next_token()? {
+                    None => None,
+                    Some(Token::Ident(s)) => Some(s),
+                    Some(t) => return Err(Error::InvalidArguments(format!("Expected a theorem name, got '{t}'")))
+                };
+                return Ok(EngineCommand::ContextCommand(ContextCommand::ShowProof(theorem)))
+            }
+            Some(t) => return Err(Error::InvalidArguments(format!("Expected 'Goals' or 'Proof', got '{t}'"))),
+            None => return Err(Error::ArgumentsRequired("Expected 'Goals' or 'Proof'".to_string()))
+        };
+
+        let full = match lxr.next_token()? {
+            None => true,
+            Some(Token::Ident(s)) if s == "compact" => false,
+            Some(t) => return Err(Error::InvalidArguments(format!("Expected 'compact', got '{t}'")))
+        };
+
+        Ok(EngineCommand::ContextCommand(ContextCommand::ShowGoals(full)))
+    }
+
+
+    fn parse_print(lxr: &mut Lexer) -> Result<EngineCommand, Error> {
+        match lxr.next_token()? {
+            Some(Token::Ident(s)) if s == "Assumptions" => (),
+            Some(t) => return Err(Error::InvalidArguments(format!("Expected 'Assumptions', got '{t}'"))),
+            None => return Err(Error::ArgumentsRequired("Expected 'Assumptions'".to_string()))
+        };
+
+        match lxr.next_token()? {
+            Some(Token::Ident(s)) => Ok(EngineCommand::ContextCommand(ContextCommand::PrintAssumptions(s))),
+            Some(t) => Err(Error::InvalidArguments(format!("Expected a theorem name, got '{t}'"))),
+            None => Err(Error::ArgumentsRequired("Expected a theorem name".to_string()))
+        }
+    }
+
+
+    fn parse_search(lxr: &mut Lexer) -> Result<EngineCommand, Error> {
+        if lxr.is_finished() {
+            return Err(Error::ArgumentsRequired("Expected a pattern".to_string()));
+        }
+        let pattern = Formula::parse(lxr)?;
+        Ok(EngineCommand::ContextCommand(ContextCommand::Search(Box::new(pattern))))
+    }
+
+
+    fn parse_reset(lxr: &mut Lexer) -> Result<EngineCommand, Error> {
+        match lxr.next_token()? {
+            Some(Token::Ident(s)) => Ok(EngineCommand::ContextCommand(ContextCommand::Reset(s))),
+            Some(t) => Err(Error::InvalidArguments(format!("Expected a theorem name, got '{t}'"))),
+            None => Err(Error::ArgumentsRequired("Expected a theorem name".to_string()))
+        }
+    }
+
+
+    fn parse_tactic(lxr: &mut Lexer, tactic_name: String) -> Result<EngineCommand, Error> {
+        let cc = match tactic_name.as_str() {
+            "apply" | "exact" => match lxr.next_token()? {
+                Some(Token::Ident(s)) if tactic_name == "apply" => ContextCommand::Apply(s),
+                Some(Token::Ident(s)) => ContextCommand::Exact(s),
+                Some(t) => return Err(Error::InvalidArguments(format!("Expected a theorem name, got '{t}'"))),
+                None => return Err(Error::ArgumentsRequired("Expected a theorem name".to_string()))
+            },
+            "assumption" => ContextCommand::Assumption,
+            "contradiction" => ContextCommand::Contradiction,
+            "tauto" => match lxr.next_token()? {
+                None => ContextCommand::Tauto(false),
+                Some(Token::Ident(s)) if s == "classical" => ContextCommand::Tauto(true),
+                Some(t) => return Err(Error::InvalidArguments(format!("Expected 'classical', got '{t}'")))
+            },
+            // Numbers are lexed as identifiers
+            "auto" => match lxr.next_token()? {
+                None => ContextCommand::Auto(tactics::auto::DEFAULT_DEPTH),
+                Some(Token::Ident(s)) => match s.parse::<usize>() {
+                    Ok(n) if n > 0 => ContextCommand::Auto(n),
+                    _ => return Err(Error::InvalidArguments(format!("Expected a depth, got '{s}'")))
+                },
+                Some(t) => return Err(Error::InvalidArguments(format!("Expected a depth, got '{t}'")))
+            },
+            "firstorder" => match lxr.next_token()? {
+                None => ContextCommand::FirstOrder(tactics::tableau::DEFAULT_BOUND),
+                Some(Token::Ident(s)) => match s.parse::<usize>() {
+                    Ok(n) => ContextCommand::FirstOrder(n),
+                    _ => return Err(Error::InvalidArguments(format!("Expected a number of instances, got '{s}'")))
+                },
+                Some(t) => return Err(Error::InvalidArguments(format!("Expected a number of instances, got '{t}'")))
+            },
+            _ => unreachable!()
+        };
+
+        Ok(EngineCommand::ContextCommand(cc))
+    }
+
+
+    fn parse_history_command(lxr: &mut Lexer, command_name: String) -> Result<EngineCommand, Error> {
+        // Numbers are lexed as identifiers
+        let number = match lxr.next_token()? {
+            None => None,
+            Some(Token::Ident(s)) => match s.parse::<usize>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => return Err(Error::InvalidArguments(format!("Expected a number of commands, got '{s}'")))
+            },
+            Some(t) => return Err(Error::InvalidArguments(format!("Expected a number of commands, got '{t}'")))
+        };
+
+        let cc = match (command_name.as_str(), number) {
+            ("undo", n) => ContextCommand::Undo(n.unwrap_or(1)),
+            ("redo", None) => ContextCommand::Redo,
+            ("redo", Some(_)) => return Err(Error::TooMuchArguments(command_name)),
+            _ => unreachable!()
+        };
+
+        Ok(EngineCommand::ContextCommand(cc))
+    }
+
+
+    fn parse_goal_command(lxr: &mut Lexer, command_name: String) -> Result<EngineCommand, Error> {
+        // Goal numbers are lexed as identifiers
+        let number = match lxr.next_token()? {
+            None => None,
+            Some(Token::Ident(s)) => match s.parse::<usize>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => return Err(Error::InvalidArguments(format!("Expected a goal number, got '{s}'")))
+            },
+            Some(t) => return Err(Error::InvalidArguments(format!("Expected a goal number, got '{t}'")))
+        };
+
+        let cc = match (command_name.as_str(), number) {
+            ("focus", Some(n)) => ContextCommand::Focus(n),
+            ("focus", None) => return Err(Error::ArgumentsRequired("Expected a goal number".to_string())),
+            ("swap", None) => ContextCommand::Swap,
+            ("swap", Some(_)) => return Err(Error::TooMuchArguments(command_name)),
+            ("rotate", n) => ContextCommand::Rotate(n.unwrap_or(1)),
+            ("defer", n) => ContextCommand::Defer(n.unwrap_or(1)),
+            _ => unreachable!()
+        };
+
+        Ok(EngineCommand::ContextCommand(cc))
+    }
+
+
     fn parse_rule(lxr: &mut Lexer, rule_name: String) -> Result<EngineCommand, Error> {
         let parse_formula = |lxr: &mut Lexer| -> Result<Box<Formula>, Error> {
             if lxr.is_finished() {
@@ -305,6 +934,21 @@ impl EngineCommand {
             Term::parse(lxr).map(Box::new)
         };
 
+        let parse_name = |lxr: &mut Lexer| -> Result<String, Error> {
+            match lxr.next_token()? {
+                Some(Token::Ident(s)) => Ok(s),
+                Some(t) => Err(Error::InvalidArguments(format!("Expected a hypothesis name, got '{t}'"))),
+                None => Err(Error::ArgumentsRequired("Expected a hypothesis name".to_string()))
+            }
+        };
+        let parse_keyword = |lxr: &mut Lexer, kw: &str| -> Result<(), Error> {
+            match lxr.next_token()? {
+                Some(Token::Ident(s)) if s == kw => Ok(()),
+                Some(t) => Err(Error::InvalidArguments(format!("Expected '{kw}', got '{t}'"))),
+                None => Err(Error::ArgumentsRequired(format!("Expected '{kw}'")))
+            }
+        };
+
         let rc = match rule_name.as_str() {
             "axiom" => RuleCommand::Axiom,
             "intro" => RuleCommand::Intro,
@@ -316,6 +960,7 @@ impl EngineCommand {
             "keep_left" => RuleCommand::KeepLeft,
             "keep_right" => RuleCommand::KeepRight,
             "from_or" => RuleCommand::FromOr(parse_formula(lxr)?),
+            "have" => EngineCommand::parse_have(lxr)?,
             "gen" => RuleCommand::Generalize(parse_term(lxr)?),
             "fix_as" => RuleCommand::FixAs(parse_term(lxr)?),
             "consider" => RuleCommand::Consider(parse_formula(lxr)?),
@@ -328,6 +973,18 @@ impl EngineCommand {
                     None => return Err(Error::ArgumentsRequired("Expected a variable name".to_string()))
                 }
             }
+            "clear" => RuleCommand::Clear(parse_name(lxr)?),
+            "rename" => {
+                let h = parse_name(lxr)?;
+                parse_keyword(lxr, "into")?;
+                RuleCommand::Rename(h, parse_name(lxr)?)
+            }
+            "move" => {
+                let h = parse_name(lxr)?;
+                parse_keyword(lxr, "after")?;
+                RuleCommand::Move(h, parse_name(lxr)?)
+            }
+            "duplicate" => RuleCommand::Duplicate(parse_name(lxr)?),
             "from_bottom" => RuleCommand::FromBottom,
             "exfalso" => RuleCommand::ExFalso(parse_formula(lxr)?),
             _ => unreachable!(), // lexer should not generate a Token::RuleName if rule_name is not in this list
@@ -337,6 +994,48 @@ impl EngineCommand {
     }
 
 
+    /// Parse the arguments of the 'have' rule: `[<H> ::] <F> [by <rule>]`
+    fn parse_have(lxr: &mut Lexer) -> Result<RuleCommand, Error> {
+        let is_by = |t: &Token| matches!(t, Token::Ident(s) if s == "by");
+
+        let (lemma, by) = lxr.read_until(is_by)?;
+        if lemma.trim().is_empty() {
+            return Err(Error::ArgumentsRequired("Expected a formula".to_string()));
+        }
+
+        // The lemma may be named: '<H> :: <F>'
+        let mut lemma_lxr = Lexer::from(lemma);
+        let (name, formula) = match lemma_lxr.read_until(|t| matches!(t, Token::DoubleColon))? {
+            (n, Some(_)) => {
+                let mut name_lxr = Lexer::from(n);
+                let name = match (name_lxr.next_token()?, name_lxr.next_token()?) {
+                    (Some(Token::Ident(s)), None) => s,
+                    _ => return Err(Error::InvalidArguments("Expected a hypothesis name".to_string()))
+                };
+                (Some(name), Formula::parse(&mut lemma_lxr)?)
+            }
+            (_, None) => (None, Formula::parse(&mut Lexer::from(lemma))?)
+        };
+
+        // The lemma may be proven right away using another rule
+        let by_rule = match by {
+            None => None,
+            Some(_) => match lxr.next_token()? {
+                Some(Token::Ident(s)) if is_rule(&s) => {
+                    match EngineCommand::parse_rule(lxr, s)? {
+                        EngineCommand::RuleCommand(rc) => Some(Box::new(rc)),
+                        _ => unreachable!()
+                    }
+                }
+                Some(t) => return Err(Error::InvalidArguments(format!("Expected a rule, got '{t}'"))),
+                None => return Err(Error::ArgumentsRequired("Expected a rule after 'by'".to_string()))
+            }
+        };
+
+        Ok(RuleCommand::Have(name, Box::new(formula), by_rule))
+    }
+
+
     /*pub fn from(command_str: &str) -> Result<EngineCommand, Error> {
         let command_str = command_str.trim();
         if command_str.is_empty() {return Err(Error::EmptyCommand)}
@@ -1,11 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Display, Formatter};
 use crate::error::Error;
-use crate::engine::command::{RuleCommandType, RuleCommandTypeDefault};
-use super::{EngineCommand, ContextCommand};
-use crate::logic::{Formula, Term};
+use crate::engine::command::{RuleCommand, RuleCommandType, RuleCommandTypeDefault};
+use super::{EngineCommand, ContextCommand, Tactic};
+use crate::logic::{is_metavariable, Formula, Sequent, Term};
 use crate::logic::rule::Rule;
 use crate::proof::Proof;
+use crate::kernel;
+use crate::tactics;
+use crate::tptp::{self, Role};
+use crate::export::{smtlib, ExportFormat};
+use crate::export::assistant::{Assistant, Statement};
+
+
+/// Maximum number of successful applications of the tactic of 'repeat'
+const MAX_REPETITIONS: usize = 100;
 
 
 //struct TermSignature {
@@ -97,6 +106,33 @@ impl Context {
 
 
 
+    /// Return the signature of the context, including the symbols used in `formulas`.
+    /// Relations & terms defined but never used are considered of arity 0.
+    pub fn signature(&self, formulas: &[&Formula]) -> Result<Signature, Error> {
+        let all = self.theorems.values().map(|f| f.as_ref()).chain(formulas.iter().copied()).collect::<Vec<_>>();
+        self.signature_of(&all)
+    }
+
+
+    /// Return the signature of `formulas`, ignoring the theorems of the context.
+    /// Relations & terms defined but not used in `formulas` are considered of arity 0.
+    pub fn signature_of(&self, formulas: &[&Formula]) -> Result<Signature, Error> {
+        let mut sig = Signature::default();
+
+        for f in formulas {
+            sig.add_formula(f, &mut vec![])?;
+        }
+        for r in self.relations.keys() {
+            sig.relations.entry(r.clone()).or_insert(0);
+        }
+        for t in self.terms.keys() {
+            sig.functions.entry(t.clone()).or_insert(0);
+        }
+
+        Ok(sig)
+    }
+
+
     /// Return an Err if the ident is already defined in the context.
     pub fn expect_not_defined(&self, ident: &str) -> Result<(), Error> {
         match self.get_type(ident) {
@@ -112,6 +148,55 @@ impl Context {
 
 
 
+/// Relations & functions with their arity, inferred from their use in formulas.
+/// Free variables are considered functions of arity 0.
+#[derive(Clone, Debug, Default)]
+pub struct Signature {
+    pub relations: BTreeMap<String, usize>,
+    pub functions: BTreeMap<String, usize>,
+}
+
+impl Signature {
+    fn add_formula(&mut self, f: &Formula, bound: &mut Vec<String>) -> Result<(), Error> {
+        match f {
+            Formula::Falsum => Ok(()),
+            Formula::Relation(r, terms) => {
+                Self::add_symbol(&mut self.relations, r, terms.len())?;
+                terms.iter().try_for_each(|t| self.add_term(t, bound))
+            }
+            Formula::Not(g) => self.add_formula(g, bound),
+            Formula::And(l, r) | Formula::Or(l, r) | Formula::Implies(l, r) => {
+                self.add_formula(l, bound)?;
+                self.add_formula(r, bound)
+            }
+            Formula::Forall(v, g) | Formula::Exists(v, g) => {
+                bound.push(v.clone());
+                let res = self.add_formula(g, bound);
+                bound.pop();
+                res
+            }
+        }
+    }
+
+    fn add_term(&mut self, t: &Term, bound: &mut Vec<String>) -> Result<(), Error> {
+        if t.1.is_empty() && bound.contains(&t.0) {
+            return Ok(())
+        }
+        Self::add_symbol(&mut self.functions, &t.0, t.1.len())?;
+        t.1.iter().try_for_each(|t| self.add_term(t, bound))
+    }
+
+    fn add_symbol(symbols: &mut BTreeMap<String, usize>, name: &str, arity: usize) -> Result<(), Error> {
+        match symbols.insert(name.to_string(), arity) {
+            Some(a) if a != arity => Err(Error::CommandError(format!("'{name}' is used with different arities ({a} and {arity})"))),
+            _ => Ok(())
+        }
+    }
+}
+
+
+
+
 /// Effect that a command had on the engine status.
 /// Returned by Engine::execute
 #[derive(Clone, Debug)]
@@ -120,7 +205,8 @@ pub enum EngineEffect {
     DefinedRelation(String),
     DefinedTerm(String),
     EnteredProofMode,
-    ExitedProofMode
+    ExitedProofMode,
+    Info(String)
 }
 
 
@@ -132,7 +218,9 @@ pub struct Engine {
     pub context: Context,
 
     pub current_proof: Option<(String, Box<Proof>)>,
-    command_stack: Vec<EngineCommand>
+    proofs: Vec<(String, Box<Proof>)>, // Derivations of the finished theorems, in order
+    command_stack: Vec<EngineCommand>,
+    undone: Vec<EngineCommand>         // Commands reverted by 'undo', the next one to redo last
 }
 
 impl Engine {
@@ -141,7 +229,9 @@ impl Engine {
             name,
             context: Context::new(),
             current_proof: None,
-            command_stack: vec![]
+            proofs: vec![],
+            command_stack: vec![],
+            undone: vec![]
         }
     }
 
@@ -154,6 +244,15 @@ impl Engine {
     pub fn check_formula(&mut self, f: &Formula, bound_vars: Vec<String>, forgiving: bool) -> Result<Vec<EngineEffect>, Error> {
         let mut effects = vec![];
         match f {
+            // Schematic variables can be used in the statement of a theorem, then in its proof
+            Formula::Relation(n, _) if is_metavariable(n) => {
+                match &self.current_proof {
+                    Some((_, p)) if !p.goal.metavariables().contains(n) => {
+                        Err(Error::InvalidFormula(f.clone(), format!("'{n}' is not a schematic variable of the current theorem")))
+                    }
+                    _ => Ok(effects)
+                }
+            }
             Formula::Relation(n, terms) => {
                 if self.context.terms.contains_key(n) {
                     Err(Error::InvalidFormula(f.clone(), format!("'{n}' used as a relation but defined as a term")))
@@ -213,6 +312,14 @@ impl Engine {
     fn check_term(&mut self, t: &Term, bound_vars: Vec<String>, forgiving: bool) -> Result<Vec<EngineEffect>, Error> {
         let mut effects = vec![];
 
+        // Metavariables are introduced by 'fix_as', and can then be used in the goals they appear in
+        if is_metavariable(&t.0) {
+            return match t.1.is_empty() && bound_vars.contains(&t.0) {
+                true => Ok(effects),
+                false => Err(Error::InvalidTerm(t.clone(), format!("'{}' is not a metavariable of the current goal", t.0)))
+            }
+        }
+
         if self.context.relations.contains_key(&t.0) {
             return Err(Error::InvalidTerm(t.clone(), format!("'{}' used as a term but defined as a relation", t.0)))
         }
@@ -232,9 +339,23 @@ impl Engine {
 
 
     pub fn get_valid_commands(&self) -> Vec<EngineCommand> {
-        match &self.current_proof {
+        let mut history = vec![];
+        if !self.command_stack.is_empty() {
+            history.push(EngineCommand::ContextCommand(ContextCommand::Undo(1)));
+            history.push(EngineCommand::ContextCommand(ContextCommand::Reset("".to_string())));
+        }
+        if !self.undone.is_empty() {
+            history.push(EngineCommand::ContextCommand(ContextCommand::Redo));
+        }
+        if !self.context.theorems.is_empty() {
+            history.push(EngineCommand::ContextCommand(ContextCommand::PrintAssumptions("".to_string())));
+            history.push(EngineCommand::ContextCommand(ContextCommand::Search(Box::default())));
+        }
+
+        let mut commands = match &self.current_proof {
             None => vec![
                 EngineCommand::ContextCommand(ContextCommand::Theorem("".to_string(), Box::default())),
+                EngineCommand::ContextCommand(ContextCommand::Axiom("".to_string(), Box::default())),
             ],
             Some((_, p)) => {
                 match p.get_applicable_rules() {
@@ -243,21 +364,66 @@ impl Engine {
                         EngineCommand::ContextCommand(ContextCommand::Admit),
                     ],
                     Some(ruletype) => {
-                        ruletype
+                        let mut commands = ruletype
                             .iter()
                             .flat_map(RuleCommandType::from_rule)
                             .map(|t| t.get_default())
                             .map(EngineCommand::RuleCommand)
-                            .collect::<Vec<_>>()
+                            .collect::<Vec<_>>();
+
+                        commands.push(EngineCommand::ContextCommand(ContextCommand::Assumption));
+                        commands.push(EngineCommand::ContextCommand(ContextCommand::Contradiction));
+                        commands.push(EngineCommand::ContextCommand(ContextCommand::Tauto(false)));
+                        commands.push(EngineCommand::ContextCommand(ContextCommand::Auto(tactics::auto::DEFAULT_DEPTH)));
+                        commands.push(EngineCommand::ContextCommand(ContextCommand::FirstOrder(tactics::tableau::DEFAULT_BOUND)));
+                        if !self.context.theorems.is_empty() {
+                            commands.push(EngineCommand::ContextCommand(ContextCommand::Apply("".to_string())));
+                            commands.push(EngineCommand::ContextCommand(ContextCommand::Exact("".to_string())));
+                        }
+
+                        if p.remaining_goals_nb() > 1 {
+                            commands.extend([
+                                ContextCommand::Focus(1),
+                                ContextCommand::Swap,
+                                ContextCommand::Rotate(1),
+                                ContextCommand::Defer(1)
+                            ].map(EngineCommand::ContextCommand));
+                        }
+
+                        commands
                     }
                 }
             }
-        }
+        };
+
+        commands.append(&mut history);
+        commands
     }
 
 
 
     pub fn execute(&mut self, command: EngineCommand) -> Result<Vec<EngineEffect>, Error> {
+        // Navigation in the history
+        match &command {
+            EngineCommand::ContextCommand(ContextCommand::Undo(n)) => return self.undo(*n),
+            EngineCommand::ContextCommand(ContextCommand::Redo) => return self.redo(),
+            EngineCommand::ContextCommand(ContextCommand::Reset(thm)) => return self.reset(thm),
+            _ => ()
+        }
+
+        let stack_size = self.command_stack.len();
+        let effects = self.execute_command(command)?;
+
+        // A new command makes the reverted ones obsolete
+        if self.command_stack.len() > stack_size {
+            self.undone.clear();
+        }
+
+        Ok(effects)
+    }
+
+
+    fn execute_command(&mut self, command: EngineCommand) -> Result<Vec<EngineEffect>, Error> {
         let mut effects = vec![];
         let proof_cpy = self.current_proof.clone();
 
@@ -281,7 +447,23 @@ impl Engine {
             }
 
 
-            (EngineCommand::ContextCommand(ContextCommand::Use(s)), Some((_, p))) => {
+            // New axiom
+            (EngineCommand::ContextCommand(ContextCommand::Axiom(..)), Some((_, p))) => {
+                return Err(Error::CommandError(format!("Already proving {}", p.goal)))
+            }
+            (EngineCommand::ContextCommand(ContextCommand::Axiom(name, formula)), None) => {
+                self.context.expect_not_defined(name)?;
+
+                let mut check_eff = self.check_formula(formula, vec![], true)?;
+                effects.append(&mut check_eff);
+
+                self.context.add_theorem(name, formula.clone())?;
+                self.command_stack.push(command.clone());
+                effects.push(EngineEffect::NewTheorem(name.clone(), *formula.clone()));
+            }
+
+
+            (EngineCommand::ContextCommand(ContextCommand::Use(s, schema, instances)), Some((_, p))) => {
                 if p.is_finished() {
                     return Err(Error::CommandError("Proof is finished".to_string()))
                 }
@@ -289,34 +471,143 @@ impl Engine {
                 match self.context.theorems.get(s) {
                     None => return Err(Error::CommandError(format!("Unknown theorem {s}"))),
                     Some(thm) => {
+                        let thm = thm.clone();
+                        let mut check_eff = self.check_schema(s, &thm, schema)?;
+
+                        let schema_instance = thm.substitute_relations(schema);
+                        let terms = resolve_instances(s, &schema_instance, instances)?;
+                        self.check_instances(&schema_instance, &terms)?;
+
                         let (_, curr_proof) = self.current_proof.as_mut().unwrap();
-                        curr_proof.add_antecedent(thm.clone())?;
+                        curr_proof.add_antecedent(s, thm, schema.clone(), terms)?;
+                        effects.append(&mut check_eff);
+                        self.command_stack.push(command);
                     }
                 }
             }
 
-            // Rule application to a proof
-            (EngineCommand::RuleCommand(rule), Some(_)) => {
-                // Check that the formulas are valid
-                match rule.to_rule() {
-                    Rule::Trans(f)
-                    | Rule::And(_, f)
-                    | Rule::FromOr(f)
-                    | Rule::Consider(f)
-                    | Rule::ExFalso(f) => {
-                        let mut e = self.check_formula(&f, vec![], true)?;
-                        effects.append(&mut e)
-                    },
-                    _ => ()
+            // Tactics, applied to a copy of the proof
+            (EngineCommand::ContextCommand(
+                cc @ (ContextCommand::Apply(_) | ContextCommand::Exact(_) | ContextCommand::Assumption
+                    | ContextCommand::Contradiction | ContextCommand::Tauto(_) | ContextCommand::FirstOrder(_))
+            ), Some((name, mut p))) => {
+                match cc {
+                    ContextCommand::Apply(thm) => tactics::apply(&mut p, &self.context.theorems, thm),
+                    ContextCommand::Exact(thm) => tactics::exact(&mut p, &self.context.theorems, thm),
+                    ContextCommand::Assumption => tactics::assumption(&mut p, &self.theorems_in_order()),
+                    ContextCommand::Contradiction => tactics::contradiction(&mut p),
+                    ContextCommand::Tauto(classical) => tactics::tauto(&mut p, *classical),
+                    ContextCommand::FirstOrder(bound) => tactics::firstorder(&mut p, *bound, &|v| self.context.get_type(v).is_some()),
+                    _ => unreachable!()
+                }?;
+                self.current_proof = Some((name, p));
+                self.command_stack.push(command);
+            }
+
+            // Proof search, whose steps are recorded instead of the command so that
+            // the script can be replayed without it
+            (EngineCommand::ContextCommand(ContextCommand::Auto(depth)), Some((_, p))) => {
+                let goal = p.current_goal().ok_or(Error::CommandError("Proof is finished".to_string()))?;
+                let steps = tactics::auto::search(goal, *depth, &|v| self.context.get_type(v).is_some())
+                    .ok_or(Error::CommandError(format!("No proof found within depth {depth}")))?;
+
+                let saved = self.clone();
+                for step in &steps {
+                    match self.apply_rule(step) {
+                        Ok(mut e) => effects.append(&mut e),
+                        Err(e) => {
+                            *self = saved;
+                            return Err(e)
+                        }
+                    }
+                    self.command_stack.push(EngineCommand::RuleCommand(step.clone()));
+                }
+
+                let script = steps.iter().map(|s| format!("{s}.")).collect::<Vec<_>>();
+                effects.push(EngineEffect::Info(format!("Proof found: {}", script.join(" "))));
+            }
+
+            // Tactic expression, applied atomically and recorded as a single command.
+            // With 'auto', the commands it applied are recorded instead, so that the
+            // script can be replayed without the proof search
+            (EngineCommand::ContextCommand(ContextCommand::Tactic(t)), Some(_)) => {
+                let saved = self.clone();
+                match self.run_tactic(t) {
+                    Ok(mut e) => effects.append(&mut e),
+                    Err(e) => {
+                        *self = saved;
+                        return Err(e)
+                    }
+                }
+
+                if !t.uses_auto() {
+                    self.command_stack.truncate(saved.command_stack.len());
+                    self.command_stack.push(command);
+                }
+            }
+
+            // Display of the goals
+            (EngineCommand::ContextCommand(ContextCommand::ShowGoals(full)), Some((_, p))) => {
+                effects.push(EngineEffect::Info(p.goals_to_string(*full)));
+            }
+
+            // Display of a derivation tree
+            (EngineCommand::ContextCommand(ContextCommand::ShowProof(None)), Some((_, p))) => {
+                effects.push(EngineEffect::Info(p.tree_to_string()));
+            }
+            (EngineCommand::ContextCommand(ContextCommand::ShowProof(Some(thm))), _) => {
+                match self.get_proof(thm) {
+                    None => return Err(Error::CommandError(format!("No proof of '{thm}' was recorded"))),
+                    Some(p) => effects.push(EngineEffect::Info(p.tree_to_string()))
+                }
+            }
+
+            // Axioms & admitted theorems a theorem relies on
+            (EngineCommand::ContextCommand(ContextCommand::PrintAssumptions(thm)), _) => {
+                let assumptions = self.assumptions(thm)?;
+
+                let txt = if assumptions.is_empty() {
+                    format!("{thm} does not depend on any axiom or admitted theorem")
+                } else {
+                    let lines = assumptions.iter()
+                        .map(|(n, f, admitted)| format!("  {n} :: {f}{}", if *admitted { " (admitted)" } else { " (axiom)" }))
+                        .collect::<Vec<_>>();
+                    format!("{thm} depends on:\n{}", lines.join("\n"))
+                };
+                effects.push(EngineEffect::Info(txt));
+            }
+
+            // Theorems matching a pattern
+            (EngineCommand::ContextCommand(ContextCommand::Search(pattern)), _) => {
+                let found = tactics::search(&self.theorems_in_order(), pattern);
+
+                let txt = if found.is_empty() {
+                    format!("No theorem matches {pattern}")
+                } else {
+                    found.iter().map(|(n, f)| format!("{n} :: {f}")).collect::<Vec<_>>().join("\n")
                 };
+                effects.push(EngineEffect::Info(txt));
+            }
 
+            // Reordering of the goals
+            (EngineCommand::ContextCommand(
+                cc @ (ContextCommand::Focus(_) | ContextCommand::Swap | ContextCommand::Rotate(_) | ContextCommand::Defer(_))
+            ), Some(_)) => {
                 let (_, curr_proof) = self.current_proof.as_mut().unwrap();
-                match curr_proof.apply(rule.clone().to_rule()) {
-                    Ok(_) => {
-                        self.command_stack.push(command);
-                    },
-                    Err(e) => return Err(e)
-                }
+                match cc {
+                    ContextCommand::Focus(n) => curr_proof.focus(*n),
+                    ContextCommand::Swap => curr_proof.swap(),
+                    ContextCommand::Rotate(n) => curr_proof.rotate(*n),
+                    ContextCommand::Defer(n) => curr_proof.defer(*n),
+                    _ => unreachable!()
+                }?;
+                self.command_stack.push(command);
+            }
+
+            // Rule application to a proof
+            (EngineCommand::RuleCommand(rule), Some(_)) => {
+                effects.append(&mut self.apply_rule(rule)?);
+                self.command_stack.push(command);
             }
 
 
@@ -331,7 +622,13 @@ impl Engine {
                     None => unreachable!(),
                     Some((n, p)) => {
                         if p.is_finished() {
+                            let unsolved = p.metavariables();
+                            if !unsolved.is_empty() {
+                                return Err(Error::CommandError(format!("Unsolved metavariables: {}", unsolved.join(", "))))
+                            }
+                            kernel::check(&p, &self.context.theorems)?;
                             self.context.add_theorem(&n, Box::new(p.goal.clone()))?;
+                            self.proofs.push((n.clone(), p.clone()));
                             self.current_proof = None;
                             self.command_stack.push(command);
                             effects.push(EngineEffect::ExitedProofMode);
@@ -358,8 +655,10 @@ impl Engine {
 
                 match proof_clone {
                     None => unreachable!(),
-                    Some((n, p)) => {
+                    Some((n, mut p)) => {
                         self.context.add_theorem(&n, Box::new(p.goal.clone()))?;
+                        p.admit();
+                        self.proofs.push((n.clone(), p.clone()));
                         self.current_proof = None;
                         self.command_stack.push(command);
                         effects.push(EngineEffect::ExitedProofMode);
@@ -379,7 +678,805 @@ impl Engine {
     }
 
 
+    /// Revert the last n commands, by replaying the previous ones on a new engine.
+    fn undo(&mut self, n: usize) -> Result<Vec<EngineEffect>, Error> {
+        match self.command_stack.len() {
+            0 => return Err(Error::CommandError("Nothing to undo".to_string())),
+            1 if n > 1 => return Err(Error::CommandError("Only 1 command can be undone".to_string())),
+            size if size < n => return Err(Error::CommandError(format!("Only {size} commands can be undone"))),
+            _ => ()
+        }
+
+        let was_proving = self.current_proof.is_some();
+
+        let mut kept = self.command_stack.clone();
+        let reverted = kept.split_off(kept.len() - n);
+
+        let mut engine = Engine::new(self.name.clone());
+        for cmd in kept {
+            engine.execute_command(cmd)?;
+        }
+        engine.undone = std::mem::take(&mut self.undone);
+        engine.undone.extend(reverted.into_iter().rev());
+        *self = engine;
+
+        Ok(self.mode_change(was_proving))
+    }
+
+
+    /// Apply again the last command reverted by 'undo'.
+    fn redo(&mut self) -> Result<Vec<EngineEffect>, Error> {
+        let cmd = match self.undone.last() {
+            None => return Err(Error::CommandError("Nothing to redo".to_string())),
+            Some(c) => c.clone()
+        };
+
+        let effects = self.execute_command(cmd)?;
+        self.undone.pop();
+
+        Ok(effects)
+    }
+
+
+    /// Revert every command since the statement of the theorem (or axiom), included.
+    fn reset(&mut self, theorem: &str) -> Result<Vec<EngineEffect>, Error> {
+        let position = self.command_stack.iter().rposition(|cmd| matches!(
+            cmd,
+            EngineCommand::ContextCommand(ContextCommand::Theorem(n, _) | ContextCommand::Axiom(n, _)) if n == theorem
+        ));
+
+        match position {
+            Some(i) => self.undo(self.command_stack.len() - i),
+            None => Err(Error::CommandError(format!("Unknown theorem {theorem}")))
+        }
+    }
+
+
+    /// Effects describing a change of mode since the previous state of the history
+    fn mode_change(&self, was_proving: bool) -> Vec<EngineEffect> {
+        match (was_proving, self.current_proof.is_some()) {
+            (false, true) => vec![EngineEffect::EnteredProofMode],
+            (true, false) => vec![EngineEffect::ExitedProofMode],
+            _ => vec![]
+        }
+    }
+
+
+    /// Apply a tactic expression to the current goal.
+    /// The state of the engine is undefined when it fails, and must be restored by the caller.
+    fn run_tactic(&mut self, tactic: &Tactic) -> Result<Vec<EngineEffect>, Error> {
+        let mut effects = vec![];
+
+        match tactic {
+            Tactic::Command(c) => effects.append(&mut self.execute_command(*c.clone())?),
+            Tactic::Then(t1, t2) => {
+                let goals_nb = self.goals_nb();
+                effects.append(&mut self.run_tactic(t1)?);
+                let created = self.goals_nb() + 1 - goals_nb;
+                effects.append(&mut self.run_on_goals(t2, created)?);
+            }
+            Tactic::Try(t) => {
+                let saved = self.clone();
+                match self.run_tactic(t) {
+                    Ok(mut e) => effects.append(&mut e),
+                    Err(_) => *self = saved
+                }
+            }
+            Tactic::Repeat(t) => {
+                // The first `pending` goals are still to be processed. The goals on which the tactic
+                // fails are moved to the end, then back to the front in order once every goal is done
+                let mut pending = 1;
+                let mut done = 0;
+                let mut applications = 0;
+
+                while pending > 0 {
+                    let saved = self.clone();
+                    let goal = self.current_goal();
+                    let goals_nb = self.goals_nb();
+
+                    match self.run_tactic(t) {
+                        // No progress, repeating would loop forever
+                        Ok(mut e) if self.goals_nb() == goals_nb && self.current_goal() == goal => effects.append(&mut e),
+                        Ok(mut e) => {
+                            applications += 1;
+                            if applications > MAX_REPETITIONS {
+                                return Err(Error::CommandError(format!("'repeat' stopped after {MAX_REPETITIONS} applications of {t}")))
+                            }
+
+                            effects.append(&mut e);
+                            pending += self.goals_nb() + 1 - goals_nb;
+                            pending -= 1;
+                            continue
+                        }
+                        Err(_) => *self = saved
+                    }
+
+                    self.move_goal(ContextCommand::Defer(1))?;
+                    pending -= 1;
+                    done += 1;
+                }
+
+                for _ in 0..done {
+                    self.move_goal(ContextCommand::Focus(self.goals_nb()))?;
+                }
+            }
+            Tactic::First(tactics) => {
+                let saved = self.clone();
+                let mut error = Error::CommandError("No tactic to apply".to_string());
+
+                for t in tactics {
+                    match self.run_tactic(t) {
+                        Ok(mut e) => {
+                            effects.append(&mut e);
+                            return Ok(effects)
+                        }
+                        Err(e) => {
+                            *self = saved.clone();
+                            error = e;
+                        }
+                    }
+                }
+                return Err(error)
+            }
+            Tactic::All(t) => effects.append(&mut self.run_on_goals(t, self.goals_nb())?)
+        }
+
+        Ok(effects)
+    }
+
+
+    /// Apply a tactic expression to each of the first n goals, keeping the order of the goals they create.
+    fn run_on_goals(&mut self, tactic: &Tactic, n: usize) -> Result<Vec<EngineEffect>, Error> {
+        let mut effects = vec![];
+        let mut created = 0;
+
+        // The goals are processed from the last one, whose created goals are then
+        // put after the ones of the previous goal
+        for i in (1..=n).rev() {
+            self.move_goal(ContextCommand::Focus(created + i))?;
+
+            let goals_nb = self.goals_nb();
+            effects.append(&mut self.run_tactic(tactic)?);
+            created += self.goals_nb() + 1 - goals_nb;
+        }
+
+        Ok(effects)
+    }
+
+
+    /// Reorder the goals with 'focus' or 'defer', recording the command unless it changes nothing
+    fn move_goal(&mut self, command: ContextCommand) -> Result<(), Error> {
+        let unchanged = match command {
+            ContextCommand::Focus(n) => n == 1,
+            ContextCommand::Defer(n) => n == self.goals_nb(),
+            _ => false
+        };
+
+        if !unchanged {
+            self.execute_command(EngineCommand::ContextCommand(command))?;
+        }
+        Ok(())
+    }
+
+
+    fn goals_nb(&self) -> usize {
+        self.current_proof.as_ref().map_or(0, |(_, p)| p.remaining_goals_nb())
+    }
+
+    fn current_goal(&self) -> Option<Sequent> {
+        self.current_proof.as_ref().and_then(|(_, p)| p.current_goal().cloned())
+    }
+
+
+    /// Apply a rule command to the current proof.
+    /// If the command fails, the proof is left untouched.
+    fn apply_rule(&mut self, rule: &RuleCommand) -> Result<Vec<EngineEffect>, Error> {
+        let mut effects = vec![];
+        let proof_cpy = self.current_proof.clone();
+
+        // Check that the formulas are valid
+        match rule.to_rule() {
+            // The eigenvariable condition of 'consider' is checked by the rule itself
+            Rule::Consider(f) => {
+                let local = self.local_variables();
+                let mut e = match f.as_ref() {
+                    Formula::Exists(v, body) if local.contains(v) => self.check_formula(body, local, true)?,
+                    _ => self.check_formula(&f, local, true)?
+                };
+                effects.append(&mut e)
+            }
+            Rule::Trans(f)
+            | Rule::And(_, f)
+            | Rule::FromOr(f)
+            | Rule::Cut(_, f)
+            | Rule::ExFalso(f) => {
+                // Variables introduced during the proof are not defined in the context
+                let mut e = self.check_formula(&f, self.local_variables(), true)?;
+                effects.append(&mut e)
+            },
+            _ => ()
+        };
+
+        self.check_eigenvariables(&rule.to_rule())?;
+
+        if let RuleCommand::Clear(h) = rule {
+            self.check_clear(h)?;
+        }
+
+        let (_, curr_proof) = self.current_proof.as_mut().ok_or(Error::CommandError("Not in proof mode".to_string()))?;
+        curr_proof.apply(rule.to_rule())?;
+
+        // The lemma introduced by 'have' can be proven right away by another rule,
+        // which must close its goal
+        if let RuleCommand::Have(_, _, Some(by)) = rule {
+            let remaining = curr_proof.remaining_goals_nb();
+
+            let res = self.apply_rule(by).and_then(|e| {
+                let (_, curr_proof) = self.current_proof.as_ref().unwrap();
+                if curr_proof.remaining_goals_nb() + 1 == remaining { Ok(e) }
+                else { Err(Error::CommandError(format!("'{by}' does not prove the lemma"))) }
+            });
+
+            match res {
+                Ok(mut e) => effects.append(&mut e),
+                Err(e) => {
+                    self.current_proof = proof_cpy;
+                    return Err(e)
+                }
+            }
+        }
+
+        Ok(effects)
+    }
+
+
+    /// Check that the formulas instantiating the schematic variables of a theorem are valid.
+    /// The schematic variables which are not instantiated must be the ones of the current theorem.
+    fn check_schema(&mut self, name: &str, thm: &Formula, schema: &[(String, Formula)]) -> Result<Vec<EngineEffect>, Error> {
+        let mut effects = vec![];
+        let metavariables = thm.metavariables();
+
+        for (i, (m, f)) in schema.iter().enumerate() {
+            if !metavariables.contains(m) {
+                return Err(Error::InvalidArguments(format!("{m} is not a schematic variable of {name}")))
+            }
+            if schema[..i].iter().any(|(n, _)| n == m) {
+                return Err(Error::InvalidArguments(format!("{m} is instantiated twice")))
+            }
+            effects.append(&mut self.check_formula(f, self.local_variables(), true)?);
+        }
+
+        let goal = self.current_proof.as_ref().map_or(vec![], |(_, p)| p.goal.metavariables());
+        if let Some(m) = metavariables.iter().find(|m| !schema.iter().any(|(n, _)| n == *m) && !goal.contains(m)) {
+            return Err(Error::InvalidArguments(format!("{m} must be instantiated")))
+        }
+
+        Ok(effects)
+    }
+
+
+    /// Check that the terms instantiating a theorem are well-formed: each symbol must be a
+    /// term of the context or a variable of the current goal, used with a single arity.
+    fn check_instances(&mut self, thm: &Formula, terms: &[Term]) -> Result<(), Error> {
+        let local = self.local_variables();
+
+        for t in terms {
+            self.check_term(t, local.clone(), false)?;
+
+            if let Some(s) = t.domain().into_iter().find(|s| !self.context.terms.contains_key(s) && !local.contains(s)) {
+                return Err(Error::InvalidTerm(t.clone(), format!("'{s}' is not defined")))
+            }
+        }
+
+        let instance = thm.instantiate(terms)?;
+        let mut formulas = vec![&instance];
+        if let Some(goal) = self.current_proof.as_ref().and_then(|(_, p)| p.current_goal()) {
+            formulas.push(&goal.consequent);
+            formulas.extend(goal.antecedents.iter().map(|h| &h.formula));
+        }
+        self.context.signature(&formulas).map(|_| ())
+    }
+
+
+    /// Return the free variables of the current goal which are not terms of the context,
+    /// i.e the variables introduced during the proof.
+    fn local_variables(&self) -> Vec<String> {
+        match self.current_proof.as_ref().and_then(|(_, p)| p.current_goal()) {
+            None => vec![],
+            Some(s) => s.domain().into_iter()
+                .filter(|v| !self.context.terms.contains_key(v))
+                .collect()
+        }
+    }
+
+
+    /// Return an Err if the rule would use a term of the context as a bound variable or as an eigenvariable.
+    /// Theorems of the context may depend on such terms, so they cannot be considered arbitrary.
+    fn check_eigenvariables(&self, rule: &Rule) -> Result<(), Error> {
+        let sequent = match self.current_proof.as_ref().and_then(|(_, p)| p.current_goal()) {
+            Some(s) => s,
+            None => return Ok(())
+        };
+
+        let introduced = match rule {
+            Rule::Intro => match sequent.consequent.as_ref() {
+                Formula::Forall(v, _) => vec![v.clone()],
+                _ => vec![]
+            },
+            Rule::Intros => {
+                let mut vars = vec![];
+                let mut f = sequent.consequent.as_ref();
+                loop {
+                    match f {
+                        Formula::Implies(_, rhs) => f = rhs,
+                        Formula::Forall(v, body) => { vars.push(v.clone()); f = body }
+                        _ => break
+                    }
+                }
+                vars
+            },
+            Rule::RenameAs(s) => vec![s.clone()],
+            _ => vec![]
+        };
+
+        let hint = match rule {
+            Rule::RenameAs(_) => "",
+            _ => ", use 'rename_as' first"
+        };
+
+        for v in introduced {
+            if self.context.get_type(&v).is_some() {
+                return Err(Error::CommandError(format!("'{v}' is already defined in the context{hint}")))
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Return an Err if the hypothesis cannot be removed from the current goal, because the goal
+    /// depends on a variable which is only introduced by this hypothesis (using 'consider' for example).
+    fn check_clear(&self, name: &str) -> Result<(), Error> {
+        let sequent = match self.current_proof.as_ref().and_then(|(_, p)| p.current_goal()) {
+            Some(s) => s,
+            None => return Ok(())
+        };
+        let hypothesis = match sequent.get(name) {
+            Some(h) => h,
+            None => return Ok(()) // reported by the rule itself
+        };
+
+        let goal_domain = sequent.consequent.domain();
+        let dependency = hypothesis.formula.domain().into_iter()
+            .filter(|v| !self.context.terms.contains_key(v))
+            .filter(|v| goal_domain.contains(v))
+            .find(|v| {
+                !sequent.antecedents.iter()
+                    .filter(|h| h.name != name)
+                    .any(|h| h.formula.domain().contains(v))
+            });
+
+        match dependency {
+            Some(v) => Err(Error::CommandError(format!("Cannot clear '{name}': the goal depends on '{v}', introduced by '{name}'"))),
+            None => Ok(())
+        }
+    }
+
+
+    /// Return the derivation of a finished theorem
+    pub fn get_proof(&self, theorem: &str) -> Option<&Proof> {
+        self.proofs.iter()
+            .find(|(n, _)| n == theorem)
+            .map(|(_, p)| p.as_ref())
+    }
+
+
+    /// Return the theorems directly used by the proof of a theorem.
+    /// Axioms do not depend on any theorem.
+    pub fn get_dependencies(&self, theorem: &str) -> Vec<String> {
+        self.get_proof(theorem).map_or(vec![], |p| p.dependencies())
+    }
+
+
+    /// Return the axioms and admitted theorems the theorem depends on (including itself),
+    /// in the order they were added to the context. The boolean is true for admitted theorems.
+    pub fn assumptions(&self, theorem: &str) -> Result<Vec<(&str, &Formula, bool)>, Error> {
+        if !self.context.theorems.contains_key(theorem) {
+            return Err(Error::CommandError(format!("Unknown theorem {theorem}")))
+        }
+
+        // Transitive closure of the dependencies
+        let mut reached = vec![theorem.to_string()];
+        let mut to_visit = vec![theorem.to_string()];
+        while let Some(thm) = to_visit.pop() {
+            for dep in self.get_dependencies(&thm) {
+                if !reached.contains(&dep) {
+                    reached.push(dep.clone());
+                    to_visit.push(dep);
+                }
+            }
+        }
+
+        let res = self.theorems_in_order().into_iter()
+            .filter(|(n, _)| reached.iter().any(|r| r == n))
+            .filter_map(|(n, f)| match self.get_proof(n) {
+                None => Some((n, f, false)),
+                Some(p) if p.is_admitted() => Some((n, f, true)),
+                Some(_) => None
+            })
+            .collect();
+
+        Ok(res)
+    }
+
+
+    /// Export the derivation of a theorem. If None is given, export every finished proof, or
+    /// the current proof for the dot format, which can draw unfinished goal graphs.
+    pub fn export(&self, format: ExportFormat, theorem: Option<&str>) -> Result<String, Error> {
+        match format {
+            ExportFormat::Tptp => return self.export_tptp(theorem),
+            ExportFormat::Smtlib => return self.export_smtlib(theorem),
+            ExportFormat::Lean => return self.export_assistant(Assistant::Lean, theorem),
+            ExportFormat::Coq => return self.export_assistant(Assistant::Coq, theorem),
+            _ => ()
+        }
+
+        let current = self.current_proof.as_ref().map(|(n, p)| (n.as_str(), p.as_ref()));
+
+        let proofs = match (theorem, current) {
+            (Some(thm), Some((n, p))) if thm == n => vec![(n, p)],
+            (Some(thm), _) => match self.get_proof(thm) {
+                Some(p) => vec![(thm, p)],
+                None => return Err(Error::CommandError(format!("No proof of '{thm}' was recorded")))
+            },
+            (None, Some(c)) if matches!(format, ExportFormat::Dot) => vec![c],
+            (None, _) => self.proofs.iter().map(|(n, p)| (n.as_str(), p.as_ref())).collect()
+        };
+
+        if proofs.is_empty() {
+            return Err(Error::CommandError("No proof to export".to_string()))
+        }
+
+        format.export(&proofs)
+    }
+
+
+    /// Write the theorems of the context as a TPTP problem, in the order they were added.
+    /// The conjecture is the given theorem, with the previous ones as premises, or the current goal
+    /// if None is given.
+    fn export_tptp(&self, theorem: Option<&str>) -> Result<String, Error> {
+        let current = self.current_proof.as_ref()
+            .and_then(|(n, p)| p.current_goal().map(|g| (n.as_str(), g.to_formula())));
+
+        if let Some(thm) = theorem {
+            if !self.context.theorems.contains_key(thm) && current.as_ref().map(|(n, _)| *n) != Some(thm) {
+                return Err(Error::CommandError(format!("Unknown theorem {thm}")))
+            }
+        }
+
+        let mut statements = vec![];
+        let mut conjecture = None;
+
+        for (name, formula) in self.theorems_in_order() {
+            if Some(name) == theorem {
+                conjecture = Some((name, formula.clone()));
+                break
+            }
+
+            let role = match self.get_proof(name) {
+                Some(p) if !p.is_admitted() => Role::Theorem,
+                _ => Role::Axiom
+            };
+            statements.push((name, role, formula.clone()));
+        }
+
+        if let Some((n, f)) = conjecture.or(current) {
+            statements.push((n, Role::Conjecture, f));
+        }
+
+        if statements.is_empty() {
+            return Err(Error::CommandError("No theorem to export".to_string()))
+        }
+
+        Ok(format!("% Context {}\n\n{}", self.name, tptp::write(&statements)))
+    }
+
+
+    /// Write the theorems of the context, up to the given one, as a Lean or Coq file.
+    fn export_assistant(&self, assistant: Assistant, theorem: Option<&str>) -> Result<String, Error> {
+        if let Some(thm) = theorem {
+            if !self.context.theorems.contains_key(thm) {
+                return Err(Error::CommandError(format!("Unknown theorem {thm}")))
+            }
+        }
+
+        let mut statements = vec![];
+        for (name, formula) in self.theorems_in_order() {
+            match self.get_proof(name) {
+                Some(p) => statements.push(Statement::Theorem(name, p)),
+                None => statements.push(Statement::Axiom(name, formula))
+            }
+            if Some(name) == theorem { break }
+        }
+
+        if statements.is_empty() {
+            return Err(Error::CommandError("No theorem to export".to_string()))
+        }
+
+        assistant.export(&self.name, &self.context.signature(&[])?, &statements)
+    }
+
+
+    /// Return the theorems of the context, in the order they were added
+    fn theorems_in_order(&self) -> Vec<(&str, &Formula)> {
+        self.command_stack.iter()
+            .filter_map(|cmd| match cmd {
+                EngineCommand::ContextCommand(ContextCommand::Axiom(n, f) | ContextCommand::Theorem(n, f)) => Some((n.as_str(), f.as_ref())),
+                _ => None
+            })
+            // Not the current or aborted proofs
+            .filter(|(n, _)| self.context.theorems.contains_key(*n))
+            .collect()
+    }
+
+
+    /// Write the current goal, or the given theorem, as an SMT-LIB2 problem.
+    fn export_smtlib(&self, theorem: Option<&str>) -> Result<String, Error> {
+        let current = self.current_proof.as_ref()
+            .and_then(|(n, p)| p.current_goal().map(|g| (n.as_str(), g.clone())));
+
+        let (name, sequent) = match (theorem, current) {
+            (Some(thm), Some((n, s))) if thm == n => (n, s),
+            (Some(thm), _) => match self.context.theorems.get(thm) {
+                Some(f) => (thm, Sequent::new(vec![], f.clone())),
+                None => return Err(Error::CommandError(format!("Unknown theorem {thm}")))
+            },
+            (None, Some(c)) => c,
+            (None, None) => return Err(Error::CommandError("Not in proof mode".to_string()))
+        };
+
+        let formulas = sequent.antecedents.iter()
+            .map(|h| &h.formula)
+            .chain([sequent.consequent.as_ref()])
+            .collect::<Vec<_>>();
+        // The theorems of the context are not exported, and may use a symbol with another arity
+        let signature = self.context.signature_of(&formulas)?;
+
+        Ok(format!("; Context {}, goal of {name}\n{}", self.name, smtlib::export(&sequent, &signature)))
+    }
+
+
     pub fn get_current_stack(&self) -> Vec<EngineCommand> {
         self.command_stack.clone()
     }
-}
\ No newline at end of file
+}
+
+
+
+/// Return the terms instantiating the leading universal quantifiers of the theorem `name`, in order.
+/// Unnamed instances are given to the first quantifiers, named ones to the quantifier of their variable.
+fn resolve_instances(name: &str, thm: &Formula, instances: &[(Option<String>, Term)]) -> Result<Vec<Term>, Error> {
+    let mut variables = vec![];
+    let mut f = thm;
+    while let Formula::Forall(v, body) = f {
+        variables.push(v.clone());
+        f = body;
+    }
+
+    let mut terms: Vec<Option<Term>> = vec![None; variables.len()];
+    for (i, (var, t)) in instances.iter().enumerate() {
+        let pos = match var {
+            None if i < variables.len() => i,
+            None => return Err(Error::InvalidArguments(format!("{name} has only {} leading universal quantifier(s)", variables.len()))),
+            Some(v) => variables.iter().position(|x| x == v)
+                .ok_or(Error::InvalidArguments(format!("{v} is not a leading universal quantifier of {name}")))?
+        };
+
+        if terms[pos].is_some() {
+            return Err(Error::InvalidArguments(format!("{} is instantiated twice", variables[pos])))
+        }
+        terms[pos] = Some(t.clone());
+    }
+
+    // Only a prefix of the quantifiers can be instantiated
+    let given = terms.iter().rposition(|t| t.is_some()).map_or(0, |i| i + 1);
+    if let Some(i) = terms[..given].iter().position(|t| t.is_none()) {
+        return Err(Error::InvalidArguments(format!("{} must be instantiated before {}", variables[i], variables[given - 1])))
+    }
+
+    Ok(terms.into_iter().take(given).flatten().collect())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::lexer::Lexer;
+
+    /// Execute the commands of the script, separated by dots.
+    /// Return the first command which fails, with its error.
+    fn run(engine: &mut Engine, script: &str) -> Result<(), (String, Error)> {
+        for command in script.split('.').map(str::trim).filter(|c| !c.is_empty()) {
+            let res = EngineCommand::parse(&mut Lexer::from(command))
+                .and_then(|c| c.map_or(Ok(vec![]), |c| engine.execute(c)));
+            if let Err(e) = res {
+                return Err((command.to_string(), e))
+            }
+        }
+        Ok(())
+    }
+
+    /// Consequents of the goals, in order
+    fn goals(engine: &Engine) -> Vec<String> {
+        let (_, proof) = engine.current_proof.as_ref().expect("in proof mode");
+        (1..=proof.remaining_goals_nb())
+            .map(|i| {
+                let mut p = proof.clone();
+                p.focus(i).unwrap();
+                p.current_goal().unwrap().consequent.to_string()
+            })
+            .collect()
+    }
+
+    fn commands(engine: &Engine) -> Vec<String> {
+        engine.command_stack.iter().map(|c| c.to_string()).collect()
+    }
+
+    fn goal(engine: &Engine) -> String {
+        let (_, proof) = engine.current_proof.as_ref().expect("in proof mode");
+        proof.current_goal().expect("unfinished proof").consequent.to_string()
+    }
+
+    /// Scripts of `tests/unsound/`, with the command which must be rejected and the error
+    const UNSOUND: [(&str, &str, &str); 7] = [
+        (include_str!("../../tests/unsound/rename_to_constant.sculpt"), "rename_as a", "'a' is already defined in the context"),
+        (include_str!("../../tests/unsound/intro_constant.sculpt"), "intro", "'x' is already defined in the context, use 'rename_as' first"),
+        (include_str!("../../tests/unsound/intro_free_in_hypothesis.sculpt"), "intro", "y is free in the hypotheses, use 'rename_as' first"),
+        (include_str!("../../tests/unsound/consider_free_in_goal.sculpt"), "consider exists y, P(y)", "y is free in the sequent"),
+        (include_str!("../../tests/unsound/consider_free_in_hypothesis.sculpt"), "consider exists y, ~P(y)", "y is free in the sequent"),
+        (include_str!("../../tests/unsound/rename_capture.sculpt"), "axiom", "Not an axiom"),
+        (include_str!("../../tests/unsound/gen_capture.sculpt"), "axiom", "Not an axiom"),
+    ];
+
+    #[test]
+    fn unsound_scripts_are_rejected() {
+        for (script, command, expected) in UNSOUND {
+            let mut engine = Engine::new("test".to_string());
+            match run(&mut engine, script) {
+                Err((c, e)) => {
+                    assert_eq!(c, command, "{script}");
+                    assert_eq!(e.to_string(), expected, "{script}");
+                }
+                Ok(()) => panic!("the script was accepted:\n{script}")
+            }
+            assert!(!engine.context.theorems.contains_key("bad"));
+        }
+    }
+
+    #[test]
+    fn rename_as_avoids_capture() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm bad :: (forall w, S(w, w)) => (exists z, (forall y, S(y, z))). intro. rename_as y.").unwrap();
+        assert_eq!(goal(&engine), "exists y, forall y', S(y', y)");
+    }
+
+    #[test]
+    fn gen_avoids_capture() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm bad :: (forall z, (P(z) /\\ (forall x, Q(z, z)))) => (P(a) /\\ (forall x, Q(x, a))). intro. gen a.").unwrap();
+        assert_eq!(goal(&engine), "forall y, P(y) /\\ (forall x, Q(x, y))");
+    }
+
+    #[test]
+    fn smtlib_export_ignores_other_theorems() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: P(a). Admit. Thm g :: P => P.").unwrap();
+        let problem = engine.export_smtlib(None).expect("the goal uses P with a single arity");
+        assert!(problem.contains("(declare-fun P () Bool)"), "{problem}");
+        assert!(engine.export_smtlib(Some("t")).is_ok());
+    }
+
+    #[test]
+    fn kernel_accepts_renamed_instances() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: forall x, (exists y, R(x, y)). Admit.").unwrap();
+        run(&mut engine, "Thm g :: exists z, R(y, z). Use t with y. axiom. Qed.").unwrap();
+        assert!(engine.context.theorems.contains_key("g"));
+
+        run(&mut engine, "Thm k :: ?A => (forall x, ?A). intro. intro. axiom. Qed.").unwrap();
+        run(&mut engine, "Thm l :: P(x) => (forall z, P(x)). Use k [A := P(x)]. axiom. Qed.").unwrap();
+        assert!(engine.context.theorems.contains_key("l"));
+    }
+
+    #[test]
+    fn reused_metavariable_is_checked_at_each_fix_as() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm lem :: forall z, R(z, z). Admit.").unwrap();
+        run(&mut engine, "Thm g :: (exists x, forall z, R(z, z)) /\\ (exists x, forall y, R(x, y)).").unwrap();
+        run(&mut engine, "split. fix_as ?w. exact lem. fix_as ?w. intro.").unwrap();
+        match run(&mut engine, "exact lem") {
+            Err((_, e)) => assert_eq!(e.to_string(), "?w cannot be instantiated by y, as y is introduced after it"),
+            Ok(()) => panic!("?w was solved by a variable introduced after it")
+        }
+    }
+
+    #[test]
+    fn unknown_tokens_are_errors() {
+        let mut engine = Engine::new("test".to_string());
+        let error = |engine: &mut Engine, script: &str| run(engine, script).expect_err(script).1.to_string();
+
+        assert_eq!(error(&mut engine, "Thm p :: P <=> P."), "Invalid formula");
+        run(&mut engine, "Thm p :: P.").unwrap();
+        assert_eq!(error(&mut engine, "intro @."), "Command 'Too much arguments where supplied' does not expect arguments");
+        assert_eq!(error(&mut engine, "try intro @."), "Unknown token '@'");
+        assert_eq!(error(&mut engine, "Use @."), "Unknown token '@'");
+    }
+
+    #[test]
+    fn sequence_keeps_goal_order() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: (A /\\ B) /\\ (C /\\ D). split; split.").unwrap();
+        assert_eq!(goals(&engine), ["A", "B", "C", "D"]);
+        assert_eq!(commands(&engine)[1..], ["split; split"]);
+
+        run(&mut engine, "undo").unwrap();
+        assert_eq!(goals(&engine), ["A /\\ B /\\ (C /\\ D)"]);
+    }
+
+    #[test]
+    fn all_applies_to_every_goal() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: (A => A) /\\ B /\\ (C => C). split. split. all: try intro.").unwrap();
+        assert_eq!(goals(&engine), ["A", "B", "C"]);
+        run(&mut engine, "all: first [axiom | intro]").unwrap_err();
+        assert_eq!(goals(&engine), ["A", "B", "C"]);
+    }
+
+    #[test]
+    fn repeat_keeps_goal_order() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: (A /\\ (B => C)) /\\ ((D => E) /\\ F). repeat first [split | intro].").unwrap();
+        assert_eq!(goals(&engine), ["A", "C", "E", "F"]);
+        assert_eq!(commands(&engine)[1..], ["repeat first [split | intro]"]);
+
+        // Only the current goal and the goals it creates are processed
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: A /\\ ((B /\\ C) /\\ D). split. repeat split.").unwrap();
+        assert_eq!(goals(&engine), ["A", "B /\\ C /\\ D"]);
+        run(&mut engine, "defer 1. repeat split.").unwrap();
+        assert_eq!(goals(&engine), ["B", "C", "D", "A"]);
+    }
+
+    #[test]
+    fn repeat_is_bounded() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: A.").unwrap();
+        let (_, e) = run(&mut engine, "repeat from_bottom").unwrap_err();
+        assert_eq!(e.to_string(), format!("'repeat' stopped after {MAX_REPETITIONS} applications of from_bottom"));
+        assert_eq!(goals(&engine), ["A"]);
+        assert_eq!(commands(&engine), ["Thm t :: A"]);
+    }
+
+    #[test]
+    fn failing_expression_changes_nothing() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: (A => A) /\\ B. split; intro; axiom.").unwrap_err();
+        assert_eq!(goals(&engine), ["(A => A) /\\ B"]);
+        assert_eq!(commands(&engine), ["Thm t :: (A => A) /\\ B"]);
+    }
+
+    #[test]
+    fn expression_using_auto_records_its_steps() {
+        let mut engine = Engine::new("test".to_string());
+        run(&mut engine, "Thm t :: (A => A) /\\ (B => B). split; auto.").unwrap();
+        let recorded = commands(&engine);
+        assert!(recorded.len() > 2 && recorded[1] == "split", "{recorded:?}");
+        assert!(!recorded.iter().any(|c| c.contains("auto")), "{recorded:?}");
+
+        // Each recorded step is reverted by its own 'undo'
+        run(&mut engine, "undo").unwrap();
+        assert_eq!(commands(&engine), recorded[..recorded.len() - 1]);
+        assert!(engine.current_proof.as_ref().is_some_and(|(_, p)| p.remaining_goals_nb() == 1));
+    }
+}
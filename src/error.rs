@@ -1,5 +1,6 @@
 use std::fmt::{Debug, Display, Formatter};
 use crate::logic::{Formula, Term};
+use crate::syntax::lexer::LexicalError;
 
 pub enum Error {
     CommandError(String),               // Error during command execution
@@ -15,6 +16,8 @@ pub enum Error {
     UnfinishedProof,
     UnexpectedEOF,
     AlreadyExists(String),
+    InvalidProof(String),               // Derivation rejected by the kernel
+    InvalidTptp(usize, String),         // Syntax error in a TPTP file (line, message)
 }
 
 impl Display for Error {
@@ -33,6 +36,8 @@ impl Display for Error {
             Error::UnfinishedProof => write!(f, "Unfinished proof"),
             Error::UnexpectedEOF => write!(f, "Unexpected end-of-file. Have you forgot a '.' ?"),
             Error::AlreadyExists(sym) => std::fmt::Display::fmt(sym, f),
+            Error::InvalidProof(e) => write!(f, "Proof rejected by the kernel: {e}"),
+            Error::InvalidTptp(line, e) => write!(f, "Invalid TPTP input (line {line}): {e}"),
         }
     }
 }
@@ -41,4 +46,10 @@ impl Debug for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         std::fmt::Display::fmt(self, f)
     }
-}
\ No newline at end of file
+}
+
+impl From<LexicalError> for Error {
+    fn from(e: LexicalError) -> Self {
+        Error::InvalidArguments(format!("{e:?}"))
+    }
+}
@@ -1,6 +1,7 @@
 use std::fs;
 use std::path::Path;
 use crate::error::Error;
+use crate::export::ExportFormat;
 use crate::engine::{Engine, EngineCommand, EngineEffect};
 use crate::syntax::lexer::Lexer;
 
@@ -62,6 +63,12 @@ impl Executor {
         Ok(())
     }
 
+    /// Export the proofs of every theorem of the file
+    pub fn export(&self, format: ExportFormat) -> Result<String, Error> {
+        self.interpreter.export(format, None)
+    }
+
+
     fn exec_one(&mut self, step: &Step) -> Result<(), Error> {
         let mut lexer = Lexer::from(step.command_txt.as_str());
         let cmd = EngineCommand::parse(&mut lexer)?;
@@ -138,7 +145,8 @@ impl Executor {
             EngineEffect::DefinedRelation(r) => println!("Defined relation {r}"),
             EngineEffect::DefinedTerm(s) => println!("Defined term {s}"),
             EngineEffect::EnteredProofMode => println!("Entered proof mode"),
-            EngineEffect::ExitedProofMode => println!("Exited proof mode")
+            EngineEffect::ExitedProofMode => println!("Exited proof mode"),
+            EngineEffect::Info(s) => println!("{s}")
         }
     }
 }
\ No newline at end of file
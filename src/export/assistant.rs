@@ -0,0 +1,418 @@
+//! Export of a context to the proof assistants Lean 4 and Coq.
+//!
+//! Terms are of an opaque type `U`, and relations are opaque `Prop`-valued functions.
+//! Each step of a derivation is translated into a tactic, and the premises of a rule
+//! become bulleted sub-proofs, in the order the tactic creates them.
+
+use crate::engine::Signature;
+use crate::error::Error;
+use crate::logic::{is_metavariable, Formula, Sequent, Term};
+use crate::logic::rule::{Rule, Side};
+use crate::logic::unification::alpha_eq;
+use crate::proof::{Proof, ProofNode};
+
+
+const SORT: &str = "U";
+
+const LEAN_RESERVED: [&str; 36] = [
+    "fun", "have", "show", "from", "by", "at", "then", "else", "if", "do", "let", "in", "match", "with",
+    "theorem", "def", "axiom", "opaque", "instance", "where", "end", "open", "variable", "universe",
+    "calc", "suffices", "sorry", "Type", "Prop", "Sort", "this", "And", "Or", "Exists", "False", "absurd"
+];
+
+const COQ_RESERVED: [&str; 40] = [
+    "as", "at", "cofix", "else", "end", "exists", "fix", "for", "forall", "fun", "if", "in", "let",
+    "match", "mod", "return", "then", "using", "where", "with", "Prop", "Set", "Type", "Theorem",
+    "Proof", "Qed", "Axiom", "Parameter", "Admitted", "True", "False", "not", "and", "or", "ex",
+    "proj1", "proj2", "or_ind", "ex_ind", "absurd"
+];
+
+
+/// Proof assistant targeted by the export
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Assistant {
+    Lean,
+    Coq
+}
+
+
+/// Theorem of a context, either stated as an axiom or proven
+pub enum Statement<'a> {
+    Axiom(&'a str, &'a Formula),
+    Theorem(&'a str, &'a Proof)
+}
+
+
+/// Tactics translating a rule application, and the order in which the tactics
+/// create the premises of the rule
+struct Step {
+    tactics: Vec<String>,
+    order: Vec<usize>
+}
+
+
+impl Assistant {
+    /// Return the source file declaring the symbols of the signature, then the statements in order.
+    pub fn export(&self, context: &str, signature: &Signature, statements: &[Statement]) -> Result<String, Error> {
+        let mut classical = false;
+        let mut body = String::new();
+
+        for s in statements {
+            let res = match s {
+                Statement::Axiom(name, f) => match self {
+                    Assistant::Lean => format!("axiom {} : {}\n", self.ident(name), self.statement(f)),
+                    Assistant::Coq => format!("Axiom {} : {}.\n", self.ident(name), self.statement(f))
+                },
+                Statement::Theorem(name, proof) => self.theorem(name, proof, &mut classical)?
+            };
+            body.push_str(&format!("\n{res}"));
+        }
+
+        let declarations = self.declarations(signature);
+        let res = match self {
+            Assistant::Lean => format!("-- Context {context}\n\n{declarations}{body}"),
+            Assistant::Coq if classical => format!("(* Context {context} *)\n\nRequire Import Classical.\n\n{declarations}{body}"),
+            Assistant::Coq => format!("(* Context {context} *)\n\n{declarations}{body}")
+        };
+
+        Ok(res)
+    }
+
+
+    fn declarations(&self, signature: &Signature) -> String {
+        let arrow = match self { Assistant::Lean => " → ", Assistant::Coq => " -> " };
+        let signature_of = |arity: usize, codomain: &str| {
+            let mut types = vec![SORT; arity];
+            types.push(codomain);
+            types.join(arrow)
+        };
+
+        let symbols = signature.functions.iter()
+            .map(|(f, a)| (f, signature_of(*a, SORT)))
+            .chain(signature.relations.iter().map(|(r, a)| (r, signature_of(*a, "Prop"))))
+            .filter(|(s, _)| !is_metavariable(s));
+
+        match self {
+            Assistant::Lean => {
+                let mut res = format!("opaque {SORT} : Type\naxiom {SORT}.nonempty : Nonempty {SORT}\nattribute [instance] {SORT}.nonempty\n\n");
+                for (s, t) in symbols {
+                    res.push_str(&format!("opaque {} : {t}\n", self.ident(s)));
+                }
+                res
+            }
+            Assistant::Coq => {
+                let mut res = format!("Parameter {SORT} : Type.\n");
+                for (s, t) in symbols {
+                    res.push_str(&format!("Parameter {} : {t}.\n", self.ident(s)));
+                }
+                res
+            }
+        }
+    }
+
+
+    fn theorem(&self, name: &str, proof: &Proof, classical: &mut bool) -> Result<String, Error> {
+        let mut script = self.script(proof, proof.root(), 0, classical)?;
+
+        // The schematic variables are parameters of the theorem
+        let metavariables = proof.goal.metavariables();
+        if !metavariables.is_empty() {
+            let names = metavariables.iter().map(|m| self.ident(m)).collect::<Vec<_>>().join(" ");
+            let intro = match self { Assistant::Lean => "intro", Assistant::Coq => "intros" };
+            script.insert(0, self.tactic(&format!("{intro} {names}")));
+        }
+
+        let script = script.into_iter()
+            .map(|l| format!("  {l}\n"))
+            .collect::<String>();
+
+        let statement = self.statement(&proof.goal);
+        let res = match self {
+            Assistant::Lean => format!("theorem {} : {statement} := by\n{script}", self.ident(name)),
+            Assistant::Coq => {
+                let end = if proof.is_admitted() { "Admitted." } else { "Qed." };
+                format!("Theorem {} : {statement}.\nProof.\n{script}{end}\n", self.ident(name))
+            }
+        };
+
+        Ok(res)
+    }
+
+
+    /// Return the lines of the tactic script proving the node. `depth` is the number of nested bullets.
+    fn script(&self, proof: &Proof, node: &ProofNode, depth: usize, classical: &mut bool) -> Result<Vec<String>, Error> {
+        let rule = match &node.rule {
+            Some(r) => r,
+            None if node.admitted => return Ok(vec![self.tactic(match self { Assistant::Lean => "sorry", Assistant::Coq => "admit" })]),
+            None => return Err(Error::CommandError(format!("The proof of {} is not finished", proof.goal)))
+        };
+
+        // Only the proof of a goal which is not a negation is classical
+        if let (Rule::FromBottom, false) = (rule, matches!(node.sequent.consequent.as_ref(), Formula::Not(_))) {
+            *classical = true;
+        }
+
+        let children = node.children.iter().map(|&c| proof.node(c)).collect::<Vec<_>>();
+        let premises = children.iter().map(|c| &c.sequent).collect::<Vec<_>>();
+        let step = self.step(rule, &node.sequent, &premises);
+
+        let mut lines = step.tactics;
+
+        if let [child] = children.as_slice() {
+            lines.extend(self.script(proof, child, depth, classical)?);
+            return Ok(lines)
+        }
+
+        let bullet = match self {
+            Assistant::Lean => "·".to_string(),
+            Assistant::Coq => ["-", "+", "*"][depth % 3].repeat(depth / 3 + 1)
+        };
+        let indent = " ".repeat(bullet.chars().count() + 1);
+
+        for i in step.order {
+            let sub = self.script(proof, children[i], depth + 1, classical)?;
+            for (j, l) in sub.into_iter().enumerate() {
+                if j == 0 { lines.push(format!("{bullet} {l}")) }
+                else { lines.push(format!("{indent}{l}")) }
+            }
+        }
+
+        Ok(lines)
+    }
+
+
+    fn step(&self, rule: &Rule, sequent: &Sequent, premises: &[&Sequent]) -> Step {
+        let lean = matches!(self, Assistant::Lean);
+        let goal = sequent.consequent.as_ref();
+        let f = |f: &Formula| self.formula(f);
+        // Name of the hypothesis added to the n-th premise
+        let new = |n: usize| self.hypothesis(&new_hypothesis(sequent, premises[n]).unwrap_or_default());
+        let step = |tactics: Vec<String>, order: Vec<usize>| Step {
+            tactics: tactics.into_iter().map(|t| self.tactic(&t)).collect(),
+            order
+        };
+
+        match rule {
+            Rule::Axiom => {
+                let h = sequent.antecedents.iter()
+                    .find(|h| alpha_eq(&h.formula, goal))
+                    .map_or(String::new(), |h| self.hypothesis(&h.name));
+                step(vec![format!("exact {h}")], vec![])
+            }
+            Rule::Intro | Rule::Intros => match goal {
+                Formula::Forall(v, _) => step(vec![format!("intro {}", self.ident(v))], vec![0]),
+                _ => step(vec![format!("intro {}", new(0))], vec![0])
+            },
+            Rule::Trans(p) => {
+                if lean { step(vec![format!("refine (?_ : ({}) → ({})) ?_", f(p), f(goal))], vec![0, 1]) }
+                else { step(vec![format!("cut ({})", f(p))], vec![0, 1]) }
+            }
+            Rule::SplitAnd => step(vec![if lean { "constructor" } else { "split" }.to_string()], vec![0, 1]),
+            Rule::And(side, p) => {
+                let (l, r) = match side { Side::Left => (f(p), f(goal)), Side::Right => (f(goal), f(p)) };
+                let tactic = match (side, lean) {
+                    (Side::Left, true) => format!("refine And.right (?_ : ({l}) ∧ ({r}))"),
+                    (Side::Right, true) => format!("refine And.left (?_ : ({l}) ∧ ({r}))"),
+                    (Side::Left, false) => format!("apply (@proj2 ({l}) ({r}))"),
+                    (Side::Right, false) => format!("apply (@proj1 ({l}) ({r}))")
+                };
+                step(vec![tactic], vec![0])
+            }
+            Rule::Keep(side) => {
+                let tactic = match (side, lean) {
+                    (Side::Left, true) => "apply Or.inl",
+                    (Side::Right, true) => "apply Or.inr",
+                    (Side::Left, false) => "left",
+                    (Side::Right, false) => "right"
+                };
+                step(vec![tactic.to_string()], vec![0])
+            }
+            Rule::FromOr(or) => {
+                let (l, r) = match or.as_ref() { Formula::Or(l, r) => (f(l), f(r)), _ => (String::new(), String::new()) };
+                if lean {
+                    step(vec![format!("refine Or.elim (?_ : {}) (fun {} => ?_) (fun {} => ?_)", f(or), new(1), new(2))], vec![0, 1, 2])
+                } else {
+                    step(vec![format!("refine (or_ind (fun {} : {l} => _) (fun {} : {r} => _) (_ : {}))", new(1), new(2), f(or))], vec![1, 2, 0])
+                }
+            }
+            Rule::Cut(_, p) => {
+                if lean { step(vec![format!("refine (fun ({} : {}) => ?_) ?_", new(1), f(p))], vec![1, 0]) }
+                else { step(vec![format!("assert ({} : {})", new(1), f(p))], vec![0, 1]) }
+            }
+            Rule::Use(thm, stated, schema, terms) => {
+                // The schematic variables which are not instantiated are those of the current theorem
+                let arguments = stated.metavariables().into_iter()
+                    .map(|m| match schema.iter().find(|(n, _)| n == &m) {
+                        Some((_, g)) => format!("({})", f(g)),
+                        None => self.ident(&m)
+                    })
+                    .chain(terms.iter().map(|t| self.argument(t)))
+                    .collect::<Vec<_>>();
+                let applied = match arguments.is_empty() {
+                    true => self.ident(thm),
+                    false => format!("({} {})", self.ident(thm), arguments.join(" "))
+                };
+                match new_hypothesis(sequent, premises[0]) {
+                    None => step(vec![], vec![0]),
+                    Some(h) if lean => {
+                        let p = premises[0].get(&h).map(|h| f(&h.formula)).unwrap_or_default();
+                        step(vec![format!("have {} : {p} := {applied}", new(0))], vec![0])
+                    }
+                    Some(_) => step(vec![format!("pose proof {applied} as {}", new(0))], vec![0])
+                }
+            }
+            Rule::Generalize(t) => {
+                let quantified = f(&premises[0].consequent);
+                if lean { step(vec![format!("refine (?_ : {quantified}) {}", self.argument(t))], vec![0]) }
+                else { step(vec![format!("refine ((_ : {quantified}) {})", self.argument(t))], vec![0]) }
+            }
+            Rule::FixAs(t) => step(vec![format!("exists {}", self.argument(t))], vec![0]),
+            Rule::Consider(ex) => {
+                let (v, body) = match ex.as_ref() { Formula::Exists(v, b) => (self.ident(v), f(b)), _ => (String::new(), String::new()) };
+                if lean {
+                    step(vec![format!("refine Exists.elim (?_ : {}) (fun {v} {} => ?_)", f(ex), new(1))], vec![0, 1])
+                } else {
+                    step(vec![format!("refine (ex_ind (fun ({v} : {SORT}) ({} : {body}) => _) (_ : {}))", new(1), f(ex))], vec![1, 0])
+                }
+            }
+            Rule::RenameAs(_) => {
+                let renamed = f(&premises[0].consequent);
+                step(vec![if lean { format!("show {renamed}") } else { format!("change ({renamed})") }], vec![0])
+            }
+            Rule::Clear(h) => step(vec![format!("clear {}", self.hypothesis(h))], vec![0]),
+            Rule::RenameHypothesis(h, k) => {
+                let (h, k) = (self.hypothesis(h), self.hypothesis(k));
+                if lean { step(vec![format!("have {k} := {h}"), format!("clear {h}")], vec![0]) }
+                else { step(vec![format!("rename {h} into {k}")], vec![0]) }
+            }
+            Rule::MoveAfter(h, k) => {
+                // The order of the hypotheses does not matter in Lean
+                if lean { step(vec![], vec![0]) }
+                else { step(vec![format!("move {} after {}", self.hypothesis(h), self.hypothesis(k))], vec![0]) }
+            }
+            Rule::Duplicate(h) => {
+                if lean { step(vec![format!("have {} := {}", new(0), self.hypothesis(h))], vec![0]) }
+                else { step(vec![format!("pose proof {} as {}", self.hypothesis(h), new(0))], vec![0]) }
+            }
+            Rule::FromBottom => {
+                // The negation ~F is introduced as F => False
+                if let Formula::Not(_) = goal { step(vec![format!("intro {}", new(0))], vec![0]) }
+                else if lean { step(vec![format!("refine Classical.byContradiction (fun {} => ?_)", new(0))], vec![0]) }
+                else { step(vec![format!("apply NNPP; intro {}", new(0))], vec![0]) }
+            }
+            Rule::ExFalso(_) => {
+                let (pos, neg) = (f(&premises[0].consequent), f(&premises[1].consequent));
+                if lean { step(vec![format!("refine absurd (?_ : {pos}) (?_ : {neg})")], vec![0, 1]) }
+                else { step(vec![format!("refine (@absurd ({pos}) False _ _)")], vec![0, 1]) }
+            }
+        }
+    }
+
+
+    fn tactic(&self, t: &str) -> String {
+        match self {
+            Assistant::Lean => t.to_string(),
+            Assistant::Coq => format!("{t}.")
+        }
+    }
+
+
+    /// Identifiers which are keywords, or names used by the export, are primed
+    fn ident(&self, name: &str) -> String {
+        let reserved = match self {
+            Assistant::Lean => LEAN_RESERVED.as_slice(),
+            Assistant::Coq => COQ_RESERVED.as_slice()
+        };
+
+        if is_metavariable(name) {
+            match self {
+                Assistant::Lean => format!("«{name}»"),
+                Assistant::Coq => format!("_{}", &name[1..])
+            }
+        }
+        else if name.starts_with(|c: char| c.is_ascii_digit()) {
+            match self {
+                Assistant::Lean => format!("«{name}»"),
+                Assistant::Coq => format!("_{name}")
+            }
+        }
+        else if reserved.contains(&name) || name == SORT {
+            format!("{name}'")
+        }
+        else {
+            name.to_string()
+        }
+    }
+
+
+    /// Formula of a theorem, universally quantified over its schematic variables
+    fn statement(&self, f: &Formula) -> String {
+        let metavariables = f.metavariables();
+        if metavariables.is_empty() {
+            return self.formula(f)
+        }
+
+        let names = metavariables.iter().map(|m| self.ident(m)).collect::<Vec<_>>().join(" ");
+        match self {
+            Assistant::Lean => format!("∀ ({names} : Prop), {}", self.formula(f)),
+            Assistant::Coq => format!("forall ({names} : Prop), {}", self.formula(f))
+        }
+    }
+
+
+    /// Hypotheses are prefixed, so that they do not shadow the theorems (a hypothesis added by
+    /// 'Use' is named after its theorem) nor the variables
+    fn hypothesis(&self, name: &str) -> String {
+        self.ident(&format!("h_{name}"))
+    }
+
+
+    fn formula(&self, f: &Formula) -> String {
+        let (not, and, or, implies, forall, exists) = match self {
+            Assistant::Lean => ("¬", "∧", "∨", "→", "∀", "∃"),
+            Assistant::Coq => ("~ ", "/\\", "\\/", "->", "forall", "exists")
+        };
+
+        // Parenthesize the operands of connectives, unless they are atomic
+        let operand = |g: &Formula| match g {
+            Formula::Falsum | Formula::Relation(..) | Formula::Not(_) => self.formula(g),
+            _ => format!("({})", self.formula(g))
+        };
+
+        match f {
+            Formula::Falsum => "False".to_string(),
+            Formula::Relation(r, args) => self.application(r, args),
+            Formula::Not(g) => format!("{not}{}", operand(g)),
+            Formula::And(l, r) => format!("{} {and} {}", operand(l), operand(r)),
+            Formula::Or(l, r) => format!("{} {or} {}", operand(l), operand(r)),
+            Formula::Implies(l, r) => format!("{} {implies} {}", operand(l), operand(r)),
+            Formula::Forall(v, g) => format!("{forall} {} : {SORT}, {}", self.ident(v), self.formula(g)),
+            Formula::Exists(v, g) => format!("{exists} {} : {SORT}, {}", self.ident(v), self.formula(g)),
+        }
+    }
+
+
+    fn application(&self, name: &str, args: &[Term]) -> String {
+        let mut res = self.ident(name);
+        for t in args {
+            res.push(' ');
+            res.push_str(&self.argument(t));
+        }
+        res
+    }
+
+
+    /// Term given as argument, parenthesized if needed
+    fn argument(&self, t: &Term) -> String {
+        if t.1.is_empty() { self.ident(&t.0) }
+        else { format!("({})", self.application(&t.0, &t.1)) }
+    }
+}
+
+
+/// Return the name of the first hypothesis of `premise` which is not in `sequent`
+fn new_hypothesis(sequent: &Sequent, premise: &Sequent) -> Option<String> {
+    premise.antecedents.iter()
+        .find(|h| sequent.get(&h.name).is_none())
+        .map(|h| h.name.clone())
+}
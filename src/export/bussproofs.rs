@@ -0,0 +1,50 @@
+//! Export of derivation trees to the LaTeX package `bussproofs`.
+
+use crate::error::Error;
+use crate::export::latex;
+use crate::proof::Proof;
+
+
+/// Return a `prooftree` environment drawing the derivation of the theorem.
+pub fn export(name: &str, proof: &Proof) -> Result<String, Error> {
+    let mut res = format!("% {name} :: {}\n\\begin{{prooftree}}\n", proof.goal);
+    write_node(proof, 0, &mut res)?;
+    res.push_str("\\end{prooftree}\n");
+    Ok(res)
+}
+
+
+/// Write the inferences of the sub-tree in post-order, as expected by bussproofs.
+fn write_node(proof: &Proof, id: usize, res: &mut String) -> Result<(), Error> {
+    let node = proof.node(id);
+    let conclusion = latex::sequent(&node.sequent);
+
+    let label = match &node.rule {
+        Some(rule) => rule.to_string(),
+        None if node.admitted => "Admitted".to_string(),
+        None => return Err(Error::CommandError(format!("The proof of {} is not finished", proof.goal)))
+    };
+
+    for &child in &node.children {
+        write_node(proof, child, res)?;
+    }
+
+    // Leaves are drawn as an inference without premise
+    let inference = match node.children.len() {
+        0 => {
+            let premise = if node.admitted { "$\\vdots$" } else { "" };
+            res.push_str(&format!("\\AxiomC{{{premise}}}\n"));
+            "UnaryInfC"
+        }
+        1 => "UnaryInfC",
+        2 => "BinaryInfC",
+        3 => "TrinaryInfC",
+        4 => "QuaternaryInfC",
+        _ => "QuinaryInfC"
+    };
+
+    res.push_str(&format!("\\RightLabel{{\\scriptsize\\texttt{{{}}}}}\n", latex::text(&label)));
+    res.push_str(&format!("\\{inference}{{${conclusion}$}}\n"));
+
+    Ok(())
+}
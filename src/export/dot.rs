@@ -0,0 +1,50 @@
+//! Export of derivation trees to the Graphviz DOT format.
+
+use crate::proof::Proof;
+
+
+const OPEN_COLOR: &str = "#f4cccc";
+const ADMITTED_COLOR: &str = "#fff2cc";
+
+
+/// Return a cluster drawing the derivation tree of the theorem. `index` is used to keep
+/// the node identifiers unique among several clusters.
+pub fn export(name: &str, proof: &Proof, index: usize) -> String {
+    let mut res = format!("  subgraph cluster_{index} {{\n    label=\"{}\";\n", escape(&format!("{name} :: {}", proof.goal)));
+    write_node(proof, 0, index, &mut res);
+    res.push_str("  }\n");
+    res
+}
+
+
+fn write_node(proof: &Proof, id: usize, index: usize, res: &mut String) {
+    let node = proof.node(id);
+
+    let mut label = node.sequent.antecedents.iter()
+        .map(|h| format!("{h}\n"))
+        .collect::<String>();
+    label.push_str(&format!("⊢ {}", node.sequent.consequent));
+
+    // Leaves are annotated with the rule closing them, as no edge carries it
+    let style = match (&node.rule, proof.goal_number(id)) {
+        (Some(rule), _) if node.children.is_empty() => { label.push_str(&format!("\n({rule})")); String::new() }
+        (Some(_), _) => String::new(),
+        (None, Some(n)) => { label.push_str(&format!("\n(goal {n})")); format!(", style=filled, fillcolor=\"{OPEN_COLOR}\"") }
+        (None, None) => { label.push_str("\n(admitted)"); format!(", style=filled, fillcolor=\"{ADMITTED_COLOR}\"") }
+    };
+
+    res.push_str(&format!("    p{index}_n{id} [label=\"{}\"{style}];\n", escape(&label)));
+
+    for &child in &node.children {
+        let rule = node.rule.as_ref().map(|r| r.to_string()).unwrap_or_default();
+        res.push_str(&format!("    p{index}_n{id} -> p{index}_n{child} [label=\"{}\"];\n", escape(&rule)));
+        write_node(proof, child, index, res);
+    }
+}
+
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,417 @@
+//! Export of derivation trees as Fitch-style proofs.
+//!
+//! The derivation tree is read from the root: each rule becomes the justification of the
+//! line concluding its sequent, the premises being derived first. Rules adding a hypothesis
+//! (`intro`, the branches of `from_or`, `consider`, `from_bottom`) open a subproof.
+
+use crate::error::Error;
+use crate::export::latex;
+use crate::logic::Formula;
+use crate::logic::rule::Rule;
+use crate::logic::unification::alpha_eq;
+use crate::proof::Proof;
+
+
+/// Natural deduction rule justifying a line
+#[derive(Clone, Debug)]
+enum FitchRule {
+    Hypothesis,
+    Reiteration,
+    ImpliesIntro,
+    ImpliesElim,
+    AndIntro,
+    AndElim,
+    OrIntro,
+    OrElim,
+    ForallIntro,
+    ForallElim,
+    ExistsIntro,
+    ExistsElim,
+    NotIntro,
+    NotElim,
+    Contradiction,
+    Renaming,
+    Theorem(String),
+    Admitted
+}
+
+impl FitchRule {
+    fn text(&self) -> String {
+        match self {
+            FitchRule::Hypothesis => "Hyp".to_string(),
+            FitchRule::Reiteration => "R".to_string(),
+            FitchRule::ImpliesIntro => "→I".to_string(),
+            FitchRule::ImpliesElim => "→E".to_string(),
+            FitchRule::AndIntro => "∧I".to_string(),
+            FitchRule::AndElim => "∧E".to_string(),
+            FitchRule::OrIntro => "∨I".to_string(),
+            FitchRule::OrElim => "∨E".to_string(),
+            FitchRule::ForallIntro => "∀I".to_string(),
+            FitchRule::ForallElim => "∀E".to_string(),
+            FitchRule::ExistsIntro => "∃I".to_string(),
+            FitchRule::ExistsElim => "∃E".to_string(),
+            FitchRule::NotIntro => "¬I".to_string(),
+            FitchRule::NotElim => "¬E".to_string(),
+            FitchRule::Contradiction => "RAA".to_string(),
+            FitchRule::Renaming => "α".to_string(),
+            FitchRule::Theorem(name) => format!("Thm {name}"),
+            FitchRule::Admitted => "Admitted".to_string(),
+        }
+    }
+
+    fn latex(&self) -> String {
+        match self {
+            FitchRule::Hypothesis => "Hyp".to_string(),
+            FitchRule::Reiteration => "R".to_string(),
+            FitchRule::ImpliesIntro => "$\\to$I".to_string(),
+            FitchRule::ImpliesElim => "$\\to$E".to_string(),
+            FitchRule::AndIntro => "$\\land$I".to_string(),
+            FitchRule::AndElim => "$\\land$E".to_string(),
+            FitchRule::OrIntro => "$\\lor$I".to_string(),
+            FitchRule::OrElim => "$\\lor$E".to_string(),
+            FitchRule::ForallIntro => "$\\forall$I".to_string(),
+            FitchRule::ForallElim => "$\\forall$E".to_string(),
+            FitchRule::ExistsIntro => "$\\exists$I".to_string(),
+            FitchRule::ExistsElim => "$\\exists$E".to_string(),
+            FitchRule::NotIntro => "$\\neg$I".to_string(),
+            FitchRule::NotElim => "$\\neg$E".to_string(),
+            FitchRule::Contradiction => "RAA".to_string(),
+            FitchRule::Renaming => "$\\alpha$".to_string(),
+            FitchRule::Theorem(name) => format!("Thm {}", latex::text(name)),
+            FitchRule::Admitted => "Admitted".to_string(),
+        }
+    }
+}
+
+
+/// Line (or range of lines, for a subproof) cited by a justification
+#[derive(Clone, Debug)]
+enum Citation {
+    Line(usize),
+    Subproof(usize, usize)
+}
+
+impl Citation {
+    fn text(&self) -> String {
+        match self {
+            Citation::Line(n) => n.to_string(),
+            Citation::Subproof(a, b) => format!("{a}–{b}")
+        }
+    }
+
+    fn latex(&self) -> String {
+        match self {
+            Citation::Line(n) => n.to_string(),
+            Citation::Subproof(a, b) => format!("{a}--{b}")
+        }
+    }
+}
+
+
+#[derive(Clone, Debug)]
+enum Line {
+    /// Start of a subproof, at the given depth
+    Start(usize),
+    /// Numbered formula, at the given depth
+    Formula(usize, Formula, FitchRule, Vec<Citation>),
+    /// Variable introduced at the start of a subproof, at the given depth
+    Flag(usize, String),
+    /// End of the hypotheses of a subproof, at the given depth
+    Separator(usize)
+}
+
+
+impl Line {
+    fn depth(&self) -> usize {
+        match self {
+            Line::Start(d) | Line::Formula(d, ..) | Line::Flag(d, _) | Line::Separator(d) => *d
+        }
+    }
+}
+
+
+/// Fitch-style proof, built from the derivation tree of a [Proof].
+pub struct Fitch {
+    lines: Vec<Line>,
+    numbered: usize
+}
+
+
+impl Fitch {
+    pub fn from_proof(proof: &Proof) -> Result<Fitch, Error> {
+        let mut fitch = Fitch { lines: vec![], numbered: 0 };
+        fitch.derive(proof, 0, 1, &[])?;
+        Ok(fitch)
+    }
+
+
+    /// Add the lines deriving the consequent of the node, at the given depth. Return the number
+    /// of the line concluding it. `scope` contains the formulas that can be cited, with their line number.
+    fn derive(&mut self, proof: &Proof, id: usize, depth: usize, scope: &[(Formula, usize)]) -> Result<usize, Error> {
+        let node = proof.node(id);
+        let goal = node.sequent.consequent.as_ref();
+
+        let rule = match &node.rule {
+            Some(r) => r,
+            None if node.admitted => return Ok(self.push(depth, goal, FitchRule::Admitted, vec![])),
+            None => return Err(Error::CommandError(format!("The proof of {} is not finished", proof.goal)))
+        };
+
+        let premise = |i: usize| node.children[i];
+
+        let line = match rule {
+            Rule::Axiom => {
+                scope.iter().rev()
+                    .find(|(f, _)| alpha_eq(f, goal))
+                    .map(|(_, n)| *n)
+                    .ok_or(Error::CommandError(format!("{goal} is not a hypothesis")))?
+            }
+
+            Rule::Intro => match goal {
+                Formula::Implies(lhs, _) => {
+                    let (start, end) = self.subproof(proof, premise(0), depth, scope, None, Some(lhs))?;
+                    self.push(depth, goal, FitchRule::ImpliesIntro, vec![Citation::Subproof(start, end)])
+                }
+                Formula::Forall(v, _) => {
+                    let (start, end) = self.subproof(proof, premise(0), depth, scope, Some(v), None)?;
+                    self.push(depth, goal, FitchRule::ForallIntro, vec![Citation::Subproof(start, end)])
+                }
+                _ => unreachable!()
+            },
+
+            Rule::Intros => return Err(Error::CommandError("'Intros' is not a primitive rule".to_string())),
+
+            Rule::Trans(_) => {
+                let implication = self.derive(proof, premise(0), depth, scope)?;
+                let antecedent = self.derive(proof, premise(1), depth, scope)?;
+                self.push(depth, goal, FitchRule::ImpliesElim, vec![Citation::Line(implication), Citation::Line(antecedent)])
+            }
+
+            Rule::SplitAnd => {
+                let lhs = self.derive(proof, premise(0), depth, scope)?;
+                let rhs = self.derive(proof, premise(1), depth, scope)?;
+                self.push(depth, goal, FitchRule::AndIntro, vec![Citation::Line(lhs), Citation::Line(rhs)])
+            }
+
+            Rule::And(..) => {
+                let and = self.derive(proof, premise(0), depth, scope)?;
+                self.push(depth, goal, FitchRule::AndElim, vec![Citation::Line(and)])
+            }
+
+            Rule::Keep(_) => {
+                let kept = self.derive(proof, premise(0), depth, scope)?;
+                self.push(depth, goal, FitchRule::OrIntro, vec![Citation::Line(kept)])
+            }
+
+            Rule::FromOr(or) => {
+                let (lhs, rhs) = match or.as_ref() {
+                    Formula::Or(lhs, rhs) => (lhs, rhs),
+                    _ => unreachable!()
+                };
+                let or = self.derive(proof, premise(0), depth, scope)?;
+                let (s1, e1) = self.subproof(proof, premise(1), depth, scope, None, Some(lhs))?;
+                let (s2, e2) = self.subproof(proof, premise(2), depth, scope, None, Some(rhs))?;
+                self.push(depth, goal, FitchRule::OrElim, vec![Citation::Line(or), Citation::Subproof(s1, e1), Citation::Subproof(s2, e2)])
+            }
+
+            Rule::Cut(_, lemma) => {
+                let lemma_line = self.derive(proof, premise(0), depth, scope)?;
+                let mut scope = scope.to_vec();
+                scope.push((*lemma.clone(), lemma_line));
+                self.derive(proof, premise(1), depth, &scope)?
+            }
+
+            Rule::Use(name, thm, schema, terms) => {
+                // An instance of a schema is a theorem
+                let mut instance = thm.substitute_relations(schema);
+                let mut line = self.push(depth, &instance, FitchRule::Theorem(name.clone()), vec![]);
+                for t in terms {
+                    instance = instance.instantiate(std::slice::from_ref(t))?;
+                    line = self.push(depth, &instance, FitchRule::ForallElim, vec![Citation::Line(line)]);
+                }
+                let mut scope = scope.to_vec();
+                scope.push((instance, line));
+                self.derive(proof, premise(0), depth, &scope)?
+            }
+
+            Rule::Generalize(_) => {
+                let forall = self.derive(proof, premise(0), depth, scope)?;
+                self.push(depth, goal, FitchRule::ForallElim, vec![Citation::Line(forall)])
+            }
+
+            Rule::FixAs(_) => {
+                let instance = self.derive(proof, premise(0), depth, scope)?;
+                self.push(depth, goal, FitchRule::ExistsIntro, vec![Citation::Line(instance)])
+            }
+
+            Rule::Consider(exists) => {
+                let (v, f) = match exists.as_ref() {
+                    Formula::Exists(v, f) => (v, f),
+                    _ => unreachable!()
+                };
+                let exists = self.derive(proof, premise(0), depth, scope)?;
+                let (start, end) = self.subproof(proof, premise(1), depth, scope, Some(v), Some(f))?;
+                self.push(depth, goal, FitchRule::ExistsElim, vec![Citation::Line(exists), Citation::Subproof(start, end)])
+            }
+
+            Rule::RenameAs(_) => {
+                let renamed = self.derive(proof, premise(0), depth, scope)?;
+                self.push(depth, goal, FitchRule::Renaming, vec![Citation::Line(renamed)])
+            }
+
+            // Structural rules do not appear in the Fitch proof
+            Rule::Clear(_) | Rule::RenameHypothesis(..) | Rule::MoveAfter(..) | Rule::Duplicate(_) => {
+                self.derive(proof, premise(0), depth, scope)?
+            }
+
+            Rule::FromBottom => {
+                let (hypothesis, fitch_rule) = match goal {
+                    Formula::Not(f) => (f.as_ref().clone(), FitchRule::NotIntro),
+                    f => (Formula::Not(Box::new(f.clone())), FitchRule::Contradiction)
+                };
+                let (start, end) = self.subproof(proof, premise(0), depth, scope, None, Some(&hypothesis))?;
+                self.push(depth, goal, fitch_rule, vec![Citation::Subproof(start, end)])
+            }
+
+            Rule::ExFalso(_) => {
+                let pos = self.derive(proof, premise(0), depth, scope)?;
+                let neg = self.derive(proof, premise(1), depth, scope)?;
+                self.push(depth, goal, FitchRule::NotElim, vec![Citation::Line(pos), Citation::Line(neg)])
+            }
+        };
+
+        Ok(line)
+    }
+
+
+    /// Add a subproof deriving the consequent of the node, starting with the given variable and hypothesis.
+    /// Return the number of its first and last lines.
+    fn subproof(
+        &mut self,
+        proof: &Proof,
+        id: usize,
+        depth: usize,
+        scope: &[(Formula, usize)],
+        variable: Option<&String>,
+        hypothesis: Option<&Formula>
+    ) -> Result<(usize, usize), Error> {
+        let depth = depth + 1;
+        let mut scope = scope.to_vec();
+        let start = self.numbered + 1;
+
+        self.lines.push(Line::Start(depth));
+        if let Some(v) = variable {
+            self.lines.push(Line::Flag(depth, v.clone()));
+        }
+        if let Some(h) = hypothesis {
+            let line = self.push(depth, h, FitchRule::Hypothesis, vec![]);
+            scope.push((h.clone(), line));
+        }
+        if variable.is_some() || hypothesis.is_some() {
+            self.lines.push(Line::Separator(depth));
+        }
+
+        let last = self.derive(proof, id, depth, &scope)?;
+
+        let formula = proof.node(id).sequent.consequent.as_ref();
+        let end = self.reiterate(formula, last, depth, start);
+
+        Ok((start, end))
+    }
+
+
+    /// If the line is before `start` (i.e outside of the current subproof), repeat it.
+    /// Return the number of the line, or of its copy.
+    fn reiterate(&mut self, formula: &Formula, line: usize, depth: usize, start: usize) -> usize {
+        if line >= start { line }
+        else { self.push(depth, formula, FitchRule::Reiteration, vec![Citation::Line(line)]) }
+    }
+
+
+    fn push(&mut self, depth: usize, formula: &Formula, rule: FitchRule, citations: Vec<Citation>) -> usize {
+        self.numbered += 1;
+        self.lines.push(Line::Formula(depth, formula.clone(), rule, citations));
+        self.numbered
+    }
+
+
+
+    /// Return the proof as plain text, one line per step, subproofs being delimited by vertical bars.
+    pub fn to_text(&self) -> String {
+        let width = self.numbered.to_string().len();
+        let bars = |depth: usize| "│ ".repeat(depth);
+
+        // Left part of each line, then its justification
+        let mut rows = vec![];
+        let mut number = 0;
+        let mut previous_depth = 1;
+        for line in &self.lines {
+            match line {
+                // Two adjacent subproofs are separated by an empty line
+                Line::Start(depth) if previous_depth >= *depth => rows.push((format!("{:width$} {}", "", bars(depth - 1)), String::new())),
+                Line::Start(_) => (),
+                Line::Formula(depth, f, rule, citations) => {
+                    number += 1;
+                    let cites = citations.iter().map(|c| c.text()).collect::<Vec<_>>().join(", ");
+                    rows.push((format!("{number:>width$} {}{f}", bars(*depth)), format!("{} {cites}", rule.text())));
+                }
+                Line::Flag(depth, v) => rows.push((format!("{:width$} {}[{v}]", "", bars(*depth)), String::new())),
+                Line::Separator(depth) => rows.push((format!("{:width$} {}├──", "", bars(depth - 1)), String::new())),
+            }
+            previous_depth = line.depth();
+        }
+
+        let column = rows.iter().map(|(l, _)| l.chars().count()).max().unwrap_or(0) + 4;
+        rows.into_iter()
+            .map(|(left, right)| {
+                if right.is_empty() { format!("{}\n", left.trim_end()) }
+                else { format!("{left}{}{}\n", " ".repeat(column - left.chars().count()), right.trim_end()) }
+            })
+            .collect()
+    }
+
+
+    /// Return the proof as a `nd` environment of the LaTeX package `fitch.sty`.
+    pub fn to_latex(&self) -> String {
+        let mut res = "\\begin{nd}\n".to_string();
+        let mut depth = 1;
+        let mut number = 0;
+
+        for line in &self.lines {
+            // Close the subproofs ended before this line
+            let line_depth = line.depth();
+            let open_depth = if let Line::Start(_) = line { line_depth - 1 } else { line_depth };
+            while depth > open_depth {
+                depth -= 1;
+                res.push_str(&format!("{}\\close\n", "  ".repeat(depth)));
+            }
+
+            let indent = "  ".repeat(depth);
+            match line {
+                Line::Formula(_, f, FitchRule::Hypothesis, _) => {
+                    number += 1;
+                    res.push_str(&format!("{indent}\\hypo{{{number}}}{{{}}}\n", latex::formula(f)));
+                }
+                Line::Formula(_, f, rule, citations) => {
+                    number += 1;
+                    let cites = citations.iter().map(|c| c.latex()).collect::<Vec<_>>().join(",");
+                    res.push_str(&format!("{indent}\\have{{{number}}}{{{}}} \\by{{{}}}{{{cites}}}\n", latex::formula(f), rule.latex()));
+                }
+                Line::Flag(_, v) => res.push_str(&format!("{indent}\\hypo{{}}{{[{}]}}\n", latex::ident(v))),
+                Line::Start(_) => {
+                    res.push_str(&format!("{indent}\\open\n"));
+                    depth += 1;
+                }
+                Line::Separator(_) => ()
+            }
+        }
+
+        while depth > 1 {
+            depth -= 1;
+            res.push_str(&format!("{}\\close\n", "  ".repeat(depth)));
+        }
+
+        res.push_str("\\end{nd}\n");
+        res
+    }
+}
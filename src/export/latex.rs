@@ -0,0 +1,82 @@
+//! LaTeX rendering of formulas and sequents, used by the LaTeX exports.
+
+use crate::logic::{Formula, Sequent, Term};
+
+
+/// Return the LaTeX (math mode) representation of a formula.
+pub fn formula(f: &Formula) -> String {
+    // Parenthesize a sub-formula if its precedence is lower than (or equal to, if `strict` is false) the parent's
+    let sub = |sub: &Formula, strict: bool| {
+        let parenthesized = if strict { sub.get_precedence() < f.get_precedence() } else { sub.get_precedence() <= f.get_precedence() };
+        if parenthesized { format!("({})", formula(sub)) } else { formula(sub) }
+    };
+
+    match f {
+        Formula::Falsum => "\\bot".to_string(),
+        Formula::Relation(r, terms) => relation(r, terms),
+        Formula::Not(g) => match g.as_ref() {
+            Formula::Falsum | Formula::Relation(..) | Formula::Not(_) => format!("\\neg {}", formula(g)),
+            _ => format!("\\neg ({})", formula(g))
+        },
+        Formula::And(lhs, rhs) => format!("{} \\land {}", sub(lhs, true), sub(rhs, false)),
+        Formula::Or(lhs, rhs) => format!("{} \\lor {}", sub(lhs, true), sub(rhs, false)),
+        Formula::Implies(lhs, rhs) => format!("{} \\to {}", sub(lhs, false), sub(rhs, true)),
+        Formula::Forall(v, g) => format!("\\forall {}.\\, {}", ident(v), formula(g)),
+        Formula::Exists(v, g) => format!("\\exists {}.\\, {}", ident(v), formula(g)),
+    }
+}
+
+
+/// Return the LaTeX (math mode) representation of a sequent. The names of the hypotheses are omitted.
+pub fn sequent(s: &Sequent) -> String {
+    let antecedents = s.antecedents.iter()
+        .map(|h| formula(&h.formula))
+        .collect::<Vec<_>>();
+
+    if antecedents.is_empty() {
+        format!("\\vdash {}", formula(&s.consequent))
+    } else {
+        format!("{} \\vdash {}", antecedents.join(", "), formula(&s.consequent))
+    }
+}
+
+
+/// Return the LaTeX (math mode) representation of a term.
+fn term(t: &Term) -> String {
+    relation(&t.0, &t.1)
+}
+
+
+fn relation(name: &str, terms: &[Term]) -> String {
+    if terms.is_empty() {
+        ident(name)
+    } else {
+        format!("{}({})", ident(name), terms.iter().map(term).collect::<Vec<_>>().join(", "))
+    }
+}
+
+
+/// Identifiers longer than one letter are written in italic text, so that they are not read as products
+pub fn ident(name: &str) -> String {
+    let escaped = name.replace('_', "\\_");
+    if name.chars().filter(|c| *c != '\'').count() > 1 {
+        let (base, primes) = escaped.split_at(escaped.trim_end_matches('\'').len());
+        format!("\\mathit{{{base}}}{primes}")
+    } else {
+        escaped
+    }
+}
+
+
+/// Escape a string to be written in LaTeX text mode.
+pub fn text(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '\\' => "\\textbackslash{}".to_string(),
+            '~' => "\\textasciitilde{}".to_string(),
+            '^' => "\\textasciicircum{}".to_string(),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => format!("\\{c}"),
+            c => c.to_string()
+        })
+        .collect()
+}
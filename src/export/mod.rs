@@ -0,0 +1,105 @@
+//! This module regroups the conversions of proofs to other formats.
+//! Contexts can also be written as TPTP problems (see [crate::tptp]), goals as SMT-LIB2 problems,
+//! and whole developments as Lean 4 or Coq files.
+
+mod latex;
+mod bussproofs;
+mod fitch;
+mod dot;
+pub mod smtlib;
+pub mod assistant;
+
+use std::fmt::{Display, Formatter};
+use crate::error::Error;
+use crate::proof::Proof;
+use fitch::Fitch;
+
+
+/// Format in which proofs can be exported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Bussproofs,
+    Fitch,
+    FitchLatex,
+    Dot,
+    Tptp,
+    Smtlib,
+    Lean,
+    Coq,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 8] = [
+        ExportFormat::Bussproofs, ExportFormat::Fitch, ExportFormat::FitchLatex, ExportFormat::Dot,
+        ExportFormat::Tptp, ExportFormat::Smtlib, ExportFormat::Lean, ExportFormat::Coq
+    ];
+
+    pub fn from_name(name: &str) -> Result<ExportFormat, Error> {
+        ExportFormat::ALL.into_iter()
+            .find(|f| f.to_string() == name)
+            .ok_or(Error::InvalidArguments(format!(
+                "Unknown export format '{name}' (expected one of: {})",
+                ExportFormat::ALL.map(|f| f.to_string()).join(", ")
+            )))
+    }
+
+    /// Extension of the files in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Bussproofs | ExportFormat::FitchLatex => "tex",
+            ExportFormat::Fitch => "txt",
+            ExportFormat::Dot => "dot",
+            ExportFormat::Tptp => "p",
+            ExportFormat::Smtlib => "smt2",
+            ExportFormat::Lean => "lean",
+            ExportFormat::Coq => "v",
+        }
+    }
+
+
+    /// Export the given proofs, in order.
+    pub fn export(&self, proofs: &[(&str, &Proof)]) -> Result<String, Error> {
+        if let ExportFormat::Tptp | ExportFormat::Smtlib | ExportFormat::Lean | ExportFormat::Coq = self {
+            return Err(Error::CommandError(format!("The {self} export works on a context, not on proofs")))
+        }
+
+        let exported = proofs.iter()
+            .enumerate()
+            .map(|(i, (name, proof))| match self {
+                ExportFormat::Bussproofs => bussproofs::export(name, proof),
+                ExportFormat::Fitch => Ok(format!("{name} :: {}\n\n{}", proof.goal, Fitch::from_proof(proof)?.to_text())),
+                ExportFormat::FitchLatex => Ok(format!("% {name} :: {}\n{}", proof.goal, Fitch::from_proof(proof)?.to_latex())),
+                ExportFormat::Dot => Ok(dot::export(name, proof, i)),
+                ExportFormat::Tptp | ExportFormat::Smtlib | ExportFormat::Lean | ExportFormat::Coq => unreachable!(),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let ExportFormat::Dot = self {
+            return Ok(format!("digraph proofs {{\n  node [shape=box];\n{}}}\n", exported.join("\n")))
+        }
+
+        let header = match self {
+            ExportFormat::Bussproofs => "% Requires \\usepackage{bussproofs}\n\n",
+            ExportFormat::Fitch => "",
+            ExportFormat::FitchLatex => "% Requires \\usepackage{fitch}\n\n",
+            _ => "",
+        };
+
+        Ok(format!("{header}{}", exported.join("\n")))
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportFormat::Bussproofs => write!(f, "bussproofs"),
+            ExportFormat::Fitch => write!(f, "fitch"),
+            ExportFormat::FitchLatex => write!(f, "fitch-latex"),
+            ExportFormat::Dot => write!(f, "dot"),
+            ExportFormat::Tptp => write!(f, "tptp"),
+            ExportFormat::Smtlib => write!(f, "smtlib"),
+            ExportFormat::Lean => write!(f, "lean"),
+            ExportFormat::Coq => write!(f, "coq"),
+        }
+    }
+}
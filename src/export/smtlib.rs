@@ -0,0 +1,71 @@
+//! Export of sequents to SMT-LIB2.
+//!
+//! Every term is of the uninterpreted sort `U`, and relations are Boolean functions.
+//! The antecedents are asserted along with the negation of the consequent, so the
+//! sequent is valid if and only if the problem is unsatisfiable.
+
+use crate::engine::Signature;
+use crate::logic::{Formula, Sequent, Term};
+
+
+const SORT: &str = "U";
+
+/// Symbols which must be quoted to be used as names
+const RESERVED: [&str; 22] = [
+    "_", "!", "as", "let", "exists", "forall", "match", "par", "and", "or", "not", "xor", "ite", "distinct",
+    "true", "false", "assert", "declare-fun", "declare-sort", "define-fun", "check-sat", SORT
+];
+
+
+/// Return the SMT-LIB2 problem checking the sequent. The signature must contain every symbol of the sequent.
+pub fn export(sequent: &Sequent, signature: &Signature) -> String {
+    let mut res = format!("(set-logic UF)\n(declare-sort {SORT} 0)\n");
+
+    for (f, arity) in &signature.functions {
+        res.push_str(&format!("(declare-fun {} ({}) {SORT})\n", symbol(f), vec![SORT; *arity].join(" ")));
+    }
+    for (r, arity) in &signature.relations {
+        res.push_str(&format!("(declare-fun {} ({}) Bool)\n", symbol(r), vec![SORT; *arity].join(" ")));
+    }
+
+    for h in &sequent.antecedents {
+        res.push_str(&format!("; {}\n(assert {})\n", h.name, formula(&h.formula)));
+    }
+    res.push_str(&format!("(assert (not {}))\n(check-sat)\n", formula(&sequent.consequent)));
+
+    res
+}
+
+
+/// Quote the identifier if it is not a valid SMT-LIB2 simple symbol
+fn symbol(name: &str) -> String {
+    let simple = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if simple && !RESERVED.contains(&name) { name.to_string() }
+    else { format!("|{name}|") }
+}
+
+
+fn formula(f: &Formula) -> String {
+    match f {
+        Formula::Falsum => "false".to_string(),
+        Formula::Relation(r, terms) => application(r, terms),
+        Formula::Not(g) => format!("(not {})", formula(g)),
+        Formula::And(l, r) => format!("(and {} {})", formula(l), formula(r)),
+        Formula::Or(l, r) => format!("(or {} {})", formula(l), formula(r)),
+        Formula::Implies(l, r) => format!("(=> {} {})", formula(l), formula(r)),
+        Formula::Forall(v, g) => format!("(forall (({} {SORT})) {})", symbol(v), formula(g)),
+        Formula::Exists(v, g) => format!("(exists (({} {SORT})) {})", symbol(v), formula(g)),
+    }
+}
+
+
+fn application(name: &str, args: &[Term]) -> String {
+    if args.is_empty() {
+        symbol(name)
+    } else {
+        let args = args.iter().map(|t| application(&t.0, &t.1)).collect::<Vec<_>>();
+        format!("({} {})", symbol(name), args.join(" "))
+    }
+}
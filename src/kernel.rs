@@ -0,0 +1,522 @@
+//! Proof-checking kernel.
+//!
+//! The kernel re-checks a finished derivation tree step by step, without relying on
+//! [Rule::apply]: for each node, it verifies that the sequents produced by the rule are
+//! exactly the premises of a natural deduction inference concluding the node's sequent.
+//!
+//! Eigenvariables introduced by ∀-introduction and ∃-elimination must not be free in the
+//! sequent they are introduced in, nor in the theorems used in the rest of their branch.
+//!
+//! Hypothesis names and order are ignored: antecedents are compared as sets of formulas,
+//! and formulas are compared up to the renaming of bound variables. Instances are computed
+//! by renaming the bound variables which would capture a variable, as the engine does.
+
+use std::collections::HashMap;
+use crate::error::Error;
+use crate::logic::{is_metavariable, Formula, Sequent, Term};
+use crate::logic::rule::{Rule, Side};
+use crate::proof::{Proof, ProofNode};
+
+
+/// Check the derivation of a finished proof. `theorems` are the theorems that the proof
+/// is allowed to use.
+/// Return an Err describing the first incorrect step, if any.
+pub fn check(proof: &Proof, theorems: &HashMap<String, Box<Formula>>) -> Result<(), Error> {
+    let root = proof.root();
+
+    if !root.sequent.antecedents.is_empty() || !alpha_eq(&root.sequent.consequent, &proof.goal) {
+        return Err(Error::InvalidProof(format!("the derivation does not conclude ⊢ {}", proof.goal)))
+    }
+
+    check_node(proof, root, theorems, &[])
+}
+
+
+
+/// `eigenvariables` are the variables introduced by the rules applied to the ancestors of the node.
+fn check_node(proof: &Proof, node: &ProofNode, theorems: &HashMap<String, Box<Formula>>, eigenvariables: &[String]) -> Result<(), Error> {
+    let rule = match &node.rule {
+        Some(r) => r,
+        None if node.admitted => return Err(Error::InvalidProof(format!("'{}' was admitted", node.sequent.to_inline_string()))),
+        None => return Err(Error::InvalidProof(format!("'{}' is not proven", node.sequent.to_inline_string())))
+    };
+
+    let children = node.children.iter()
+        .map(|&id| &proof.node(id).sequent)
+        .collect::<Vec<_>>();
+
+    check_step(rule, &node.sequent, &children, theorems, eigenvariables).map_err(|e| {
+        Error::InvalidProof(format!("incorrect step '{rule}' on '{}': {e}", node.sequent.to_inline_string()))
+    })?;
+
+    // Eigenvariable introduced by this step, in the scope of its last premise
+    let introduced = match (rule, node.sequent.consequent.as_ref()) {
+        (Rule::Intro, Formula::Forall(v, _)) => Some(v),
+        (Rule::Consider(ex), _) => match ex.as_ref() {
+            Formula::Exists(v, _) => Some(v),
+            _ => None
+        },
+        _ => None
+    };
+
+    for (i, &id) in node.children.iter().enumerate() {
+        match introduced {
+            Some(v) if i + 1 == node.children.len() => {
+                let mut scope = eigenvariables.to_vec();
+                scope.push(v.clone());
+                check_node(proof, proof.node(id), theorems, &scope)?
+            }
+            _ => check_node(proof, proof.node(id), theorems, eigenvariables)?
+        }
+    }
+
+    Ok(())
+}
+
+
+
+/// Check that `premises` are the premises of a `rule` inference concluding `conclusion`.
+fn check_step(
+    rule: &Rule,
+    conclusion: &Sequent,
+    premises: &[&Sequent],
+    theorems: &HashMap<String, Box<Formula>>,
+    eigenvariables: &[String]
+) -> Result<(), String> {
+    let gamma = hypotheses(conclusion);
+    let goal = conclusion.consequent.as_ref();
+
+    // Premise with the same hypotheses as the conclusion, plus `extra`
+    let with = |extra: &[&Formula], consequent: &Formula| {
+        let mut hyps = gamma.clone();
+        hyps.extend(extra.iter().map(|&f| f.clone()));
+        (hyps, consequent.clone())
+    };
+
+    let expected = match rule {
+        Rule::Axiom => {
+            if !gamma.iter().any(|h| alpha_eq(h, goal)) {
+                return Err("the goal is not a hypothesis".to_string())
+            }
+            vec![]
+        }
+
+        Rule::Intro => match goal {
+            Formula::Implies(lhs, rhs) => vec![with(&[lhs], rhs)],
+            Formula::Forall(v, f) => {
+                if gamma.iter().any(|h| is_free(v, h)) {
+                    return Err(format!("{v} is free in the hypotheses"))
+                }
+                vec![with(&[], f)]
+            }
+            _ => return Err("the goal is neither an implication nor universal".to_string())
+        },
+
+        Rule::Intros => return Err("'Intros' is not a primitive rule".to_string()),
+
+        Rule::Trans(p) => vec![
+            with(&[], &Formula::Implies(p.clone(), Box::new(goal.clone()))),
+            with(&[], p)
+        ],
+
+        Rule::SplitAnd => match goal {
+            Formula::And(lhs, rhs) => vec![with(&[], lhs), with(&[], rhs)],
+            _ => return Err("the goal is not a conjunction".to_string())
+        },
+
+        Rule::And(side, p) => {
+            let and = match side {
+                Side::Left => Formula::And(p.clone(), Box::new(goal.clone())),
+                Side::Right => Formula::And(Box::new(goal.clone()), p.clone())
+            };
+            vec![with(&[], &and)]
+        }
+
+        Rule::Keep(side) => match (goal, side) {
+            (Formula::Or(lhs, _), Side::Left) => vec![with(&[], lhs)],
+            (Formula::Or(_, rhs), Side::Right) => vec![with(&[], rhs)],
+            _ => return Err("the goal is not a disjunction".to_string())
+        },
+
+        Rule::FromOr(or) => match or.as_ref() {
+            Formula::Or(lhs, rhs) => vec![with(&[], or), with(&[lhs], goal), with(&[rhs], goal)],
+            _ => return Err("the eliminated formula is not a disjunction".to_string())
+        },
+
+        Rule::Cut(_, p) => vec![with(&[], p), with(&[p], goal)],
+
+        Rule::Use(name, thm, schema, terms) => {
+            match theorems.get(name) {
+                Some(t) if alpha_eq(t, thm) => {
+                    if let Some(v) = eigenvariables.iter().find(|v| is_free(v, thm)) {
+                        return Err(format!("the eigenvariable {v} is free in '{name}'"))
+                    }
+                    if let Some((m, _)) = schema.iter().find(|(m, _)| !is_metavariable(m)) {
+                        return Err(format!("{m} is not a schematic variable"))
+                    }
+                    // Instance of the schema, then successive ∀-eliminations.
+                    // The instantiation terms may use the eigenvariables
+                    let mut instance = substitute_schema(thm, schema);
+                    for t in terms {
+                        instance = match instance {
+                            Formula::Forall(x, f) => substitute(&f, &x, t),
+                            _ => return Err(format!("'{name}' has less than {} leading universal quantifiers", terms.len()))
+                        };
+                    }
+                    vec![with(&[&instance], goal)]
+                }
+                Some(_) => return Err(format!("'{name}' does not state {thm}")),
+                None => return Err(format!("unknown theorem '{name}'"))
+            }
+        }
+
+        Rule::Generalize(t) => {
+            // The premise is ∀x, F with F[x := t] being the goal
+            let premise = single(premises)?;
+            match premise.consequent.as_ref() {
+                Formula::Forall(x, f) => {
+                    let instance = substitute(f, x, t);
+                    if !alpha_eq(&instance, goal) {
+                        return Err(format!("{} is not an instance of {}", goal, premise.consequent))
+                    }
+                    vec![with(&[], &premise.consequent)]
+                }
+                _ => return Err("the premise is not universal".to_string())
+            }
+        }
+
+        Rule::FixAs(t) => match goal {
+            Formula::Exists(x, f) => {
+                let instance = substitute(f, x, t);
+                vec![with(&[], &instance)]
+            }
+            _ => return Err("the goal is not existential".to_string())
+        },
+
+        Rule::Consider(ex) => match ex.as_ref() {
+            Formula::Exists(x, f) => {
+                if is_free(x, goal) || gamma.iter().any(|h| is_free(x, h)) {
+                    return Err(format!("{x} is free in the sequent"))
+                }
+                vec![with(&[], ex), with(&[f], goal)]
+            }
+            _ => return Err("the considered formula is not existential".to_string())
+        },
+
+        // Renaming of a bound variable: the premise is checked up to alpha-equivalence
+        Rule::RenameAs(_) => vec![with(&[], goal)],
+
+        // Structural rules: the premise is the conclusion with some hypotheses possibly removed
+        Rule::Clear(_) | Rule::RenameHypothesis(..) | Rule::MoveAfter(..) | Rule::Duplicate(_) => {
+            let premise = single(premises)?;
+            let hyps = hypotheses(premise);
+            if let Some(h) = hyps.iter().find(|h| !gamma.iter().any(|g| alpha_eq(g, h))) {
+                return Err(format!("{h} is not a hypothesis"))
+            }
+            vec![(hyps, goal.clone())]
+        }
+
+        Rule::FromBottom => {
+            let negation = match goal {
+                Formula::Not(f) => f.as_ref().clone(),
+                f => Formula::Not(Box::new(f.clone()))
+            };
+            vec![with(&[&negation], &Formula::Falsum)]
+        }
+
+        Rule::ExFalso(p) => {
+            if goal != &Formula::Falsum {
+                return Err("the goal is not falsum".to_string())
+            }
+            let (pos, neg) = match p.as_ref() {
+                Formula::Not(f) => (f.as_ref().clone(), p.as_ref().clone()),
+                f => (f.clone(), Formula::Not(p.clone()))
+            };
+            vec![with(&[], &pos), with(&[], &neg)]
+        }
+    };
+
+    if expected.len() != premises.len() {
+        return Err(format!("expected {} premises, found {}", expected.len(), premises.len()))
+    }
+
+    for ((hyps, consequent), premise) in expected.iter().zip(premises) {
+        if !alpha_eq(consequent, &premise.consequent) || !same_hypotheses(hyps, &hypotheses(premise)) {
+            return Err(format!("unexpected premise '{}'", premise.to_inline_string()))
+        }
+    }
+
+    Ok(())
+}
+
+
+
+fn single<'a>(premises: &[&'a Sequent]) -> Result<&'a Sequent, String> {
+    match premises {
+        [p] => Ok(p),
+        _ => Err(format!("expected 1 premise, found {}", premises.len()))
+    }
+}
+
+
+fn hypotheses(sequent: &Sequent) -> Vec<Formula> {
+    sequent.antecedents.iter().map(|h| h.formula.clone()).collect()
+}
+
+
+/// Return whether both lists contain the same formulas, up to alpha-equivalence
+fn same_hypotheses(a: &[Formula], b: &[Formula]) -> bool {
+    a.iter().all(|f| b.iter().any(|g| alpha_eq(f, g)))
+        && b.iter().all(|f| a.iter().any(|g| alpha_eq(f, g)))
+}
+
+
+
+/// Return whether the variable `v` is free in the formula.
+fn is_free(v: &str, formula: &Formula) -> bool {
+    match formula {
+        Formula::Falsum => false,
+        Formula::Relation(_, terms) => terms.iter().any(|t| t.domain().iter().any(|s| s == v)),
+        Formula::Not(f) => is_free(v, f),
+        Formula::Or(f1, f2) | Formula::And(f1, f2) | Formula::Implies(f1, f2) => is_free(v, f1) || is_free(v, f2),
+        Formula::Forall(x, f) | Formula::Exists(x, f) => x != v && is_free(v, f)
+    }
+}
+
+
+
+/// Replace the free occurrences of the variable `v` by `t` in the formula.
+/// Bound variables which would capture a variable of `t` are renamed.
+fn substitute(formula: &Formula, v: &str, t: &Term) -> Formula {
+    let sub = |f: &Formula| Box::new(substitute(f, v, t));
+
+    match formula {
+        Formula::Falsum => Formula::Falsum,
+        Formula::Relation(r, terms) => Formula::Relation(r.clone(), terms.iter().map(|u| substitute_term(u, v, t)).collect()),
+        Formula::Not(f) => Formula::Not(sub(f)),
+        Formula::Or(f1, f2) => Formula::Or(sub(f1), sub(f2)),
+        Formula::And(f1, f2) => Formula::And(sub(f1), sub(f2)),
+        Formula::Implies(f1, f2) => Formula::Implies(sub(f1), sub(f2)),
+        Formula::Forall(x, _) | Formula::Exists(x, _) if x == v => formula.clone(),
+        Formula::Forall(x, f) | Formula::Exists(x, f) => {
+            let (x, f) = if is_free(v, f) && t.domain().contains(x) {
+                rename_bound(x, f, |y| y == v || t.domain().iter().any(|s| s == y))
+            } else {
+                (x.clone(), f.as_ref().clone())
+            };
+            quantifier(formula, x, sub(&f))
+        }
+    }
+}
+
+/// Replace the schematic variables `?A` by their formulas in the formula.
+/// Bound variables which would capture a variable of one of those formulas are renamed.
+fn substitute_schema(formula: &Formula, schema: &[(String, Formula)]) -> Formula {
+    let sub = |f: &Formula| Box::new(substitute_schema(f, schema));
+
+    match formula {
+        Formula::Relation(r, terms) if terms.is_empty() => match schema.iter().find(|(m, _)| m == r) {
+            Some((_, f)) => f.clone(),
+            None => formula.clone()
+        },
+        Formula::Falsum | Formula::Relation(..) => formula.clone(),
+        Formula::Not(f) => Formula::Not(sub(f)),
+        Formula::Or(f1, f2) => Formula::Or(sub(f1), sub(f2)),
+        Formula::And(f1, f2) => Formula::And(sub(f1), sub(f2)),
+        Formula::Implies(f1, f2) => Formula::Implies(sub(f1), sub(f2)),
+        Formula::Forall(x, f) | Formula::Exists(x, f) => {
+            let (x, f) = if schema.iter().any(|(m, g)| is_free(x, g) && has_relation(f, m)) {
+                rename_bound(x, f, |y| schema.iter().any(|(_, g)| is_free(y, g)))
+            } else {
+                (x.clone(), f.as_ref().clone())
+            };
+            quantifier(formula, x, sub(&f))
+        }
+    }
+}
+
+/// Rename the variable `x` bound over `body` to a variable which is not free in `body`
+/// and which is not `taken`. Return the new variable and the renamed body.
+fn rename_bound(x: &str, body: &Formula, taken: impl Fn(&str) -> bool) -> (String, Formula) {
+    let fresh = (1..)
+        .map(|i| format!("{x}{}", "'".repeat(i)))
+        .find(|y| !taken(y) && !is_free(y, body))
+        .expect("Infinite iterator");
+    let renamed = substitute(body, x, &Term(fresh.clone(), vec![]));
+    (fresh, renamed)
+}
+
+/// Quantifier of the same kind as `formula`, binding `x` over `body`
+fn quantifier(formula: &Formula, x: String, body: Box<Formula>) -> Formula {
+    match formula {
+        Formula::Forall(..) => Formula::Forall(x, body),
+        _ => Formula::Exists(x, body)
+    }
+}
+
+/// Return whether the relation `r` is used in the formula
+fn has_relation(formula: &Formula, r: &str) -> bool {
+    match formula {
+        Formula::Falsum => false,
+        Formula::Relation(s, _) => s == r,
+        Formula::Not(f) | Formula::Forall(_, f) | Formula::Exists(_, f) => has_relation(f, r),
+        Formula::Or(f1, f2) | Formula::And(f1, f2) | Formula::Implies(f1, f2) => has_relation(f1, r) || has_relation(f2, r)
+    }
+}
+
+fn substitute_term(term: &Term, v: &str, t: &Term) -> Term {
+    if term.0 == v && term.1.is_empty() {
+        t.clone()
+    } else {
+        Term(term.0.clone(), term.1.iter().map(|u| substitute_term(u, v, t)).collect())
+    }
+}
+
+
+
+/// Return whether both formulas are equal, up to the renaming of bound variables.
+fn alpha_eq(a: &Formula, b: &Formula) -> bool {
+    alpha_eq_bound(a, b, &mut vec![])
+}
+
+/// `bound` contains the pairs of variables bound by the enclosing quantifiers, innermost last.
+fn alpha_eq_bound(a: &Formula, b: &Formula, bound: &mut Vec<(String, String)>) -> bool {
+    match (a, b) {
+        (Formula::Falsum, Formula::Falsum) => true,
+        (Formula::Relation(r1, t1), Formula::Relation(r2, t2)) => {
+            r1 == r2 && t1.len() == t2.len() && t1.iter().zip(t2).all(|(u1, u2)| alpha_eq_term(u1, u2, bound))
+        }
+        (Formula::Not(f1), Formula::Not(f2)) => alpha_eq_bound(f1, f2, bound),
+        (Formula::Or(l1, r1), Formula::Or(l2, r2))
+        | (Formula::And(l1, r1), Formula::And(l2, r2))
+        | (Formula::Implies(l1, r1), Formula::Implies(l2, r2)) => {
+            alpha_eq_bound(l1, l2, bound) && alpha_eq_bound(r1, r2, bound)
+        }
+        (Formula::Forall(x1, f1), Formula::Forall(x2, f2))
+        | (Formula::Exists(x1, f1), Formula::Exists(x2, f2)) => {
+            bound.push((x1.clone(), x2.clone()));
+            let res = alpha_eq_bound(f1, f2, bound);
+            bound.pop();
+            res
+        }
+        _ => false
+    }
+}
+
+fn alpha_eq_term(a: &Term, b: &Term, bound: &[(String, String)]) -> bool {
+    let pos_a = bound.iter().rposition(|(x, _)| x == &a.0);
+    let pos_b = bound.iter().rposition(|(_, y)| y == &b.0);
+
+    let same_symbol = match (pos_a, pos_b) {
+        (None, None) => a.0 == b.0,
+        (i, j) => i == j
+    };
+
+    same_symbol && a.1.len() == b.1.len() && a.1.iter().zip(&b.1).all(|(u, v)| alpha_eq_term(u, v, bound))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::Hypothesis;
+    use crate::syntax::lexer::Lexer;
+
+    fn formula(s: &str) -> Formula {
+        Formula::parse(&mut Lexer::from(s)).expect("valid formula")
+    }
+
+    fn sequent(hyps: &[&str], consequent: &str) -> Sequent {
+        let antecedents = hyps.iter().enumerate().map(|(i, h)| Hypothesis::new(&format!("H{i}"), formula(h))).collect();
+        Sequent::new(antecedents, Box::new(formula(consequent)))
+    }
+
+    fn prove(goal: &str, rules: Vec<Rule>) -> Proof {
+        let mut proof = Proof::start(Box::new(formula(goal)));
+        for rule in rules {
+            proof.apply(rule).expect("applicable rule");
+        }
+        assert!(proof.is_finished());
+        proof
+    }
+
+    #[test]
+    fn accepts_derivation() {
+        let proof = prove("forall x, P(x) => P(x)", vec![Rule::Intro, Rule::Intro, Rule::Axiom]);
+        assert!(check(&proof, &HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn accepts_use_of_theorem() {
+        let theorems = HashMap::from([("t".to_string(), Box::new(formula("forall x, P(x)")))]);
+        let rule = Rule::Use("t".to_string(), theorems["t"].clone(), vec![], vec![Term("a".to_string(), vec![])]);
+        let proof = prove("P(a)", vec![rule, Rule::Axiom]);
+        assert!(check(&proof, &theorems).is_ok());
+    }
+
+    #[test]
+    fn substitution_avoids_capture() {
+        let f = formula("exists y, R(x, y)");
+        let instance = substitute(&f, "x", &Term("y".to_string(), vec![]));
+        assert!(alpha_eq(&instance, &formula("exists z, R(y, z)")), "{instance}");
+
+        let f = formula("?A => (forall x, ?A)");
+        let instance = substitute_schema(&f, &[("?A".to_string(), formula("P(x)"))]);
+        assert!(alpha_eq(&instance, &formula("P(x) => (forall z, P(x))")), "{instance}");
+    }
+
+    #[test]
+    fn rejects_wrong_premise() {
+        let conclusion = sequent(&[], "A /\\ B");
+        let premises = [sequent(&[], "A"), sequent(&[], "C")];
+        let res = check_step(&Rule::SplitAnd, &conclusion, &premises.iter().collect::<Vec<_>>(), &HashMap::new(), &[]);
+        assert_eq!(res, Err("unexpected premise '⊢ C'".to_string()));
+    }
+
+    #[test]
+    fn rejects_missing_premise() {
+        let conclusion = sequent(&[], "A /\\ B");
+        let premises = [sequent(&[], "A")];
+        let res = check_step(&Rule::SplitAnd, &conclusion, &premises.iter().collect::<Vec<_>>(), &HashMap::new(), &[]);
+        assert_eq!(res, Err("expected 2 premises, found 1".to_string()));
+    }
+
+    #[test]
+    fn rejects_eigenvariable_free_in_hypotheses() {
+        let conclusion = sequent(&["P(x)"], "forall x, Q(x)");
+        let premises = [sequent(&["P(x)"], "Q(x)")];
+        let res = check_step(&Rule::Intro, &conclusion, &premises.iter().collect::<Vec<_>>(), &HashMap::new(), &[]);
+        assert_eq!(res, Err("x is free in the hypotheses".to_string()));
+    }
+
+    #[test]
+    fn rejects_eigenvariable_free_in_used_theorem() {
+        let theorems = HashMap::from([("t".to_string(), Box::new(formula("P(y)")))]);
+        let rule = Rule::Use("t".to_string(), theorems["t"].clone(), vec![], vec![]);
+        let conclusion = sequent(&[], "Q");
+        let premises = [sequent(&["P(y)"], "Q")];
+        let res = check_step(&rule, &conclusion, &premises.iter().collect::<Vec<_>>(), &theorems, &["y".to_string()]);
+        assert_eq!(res, Err("the eigenvariable y is free in 't'".to_string()));
+    }
+
+    #[test]
+    fn rejects_use_of_different_statement() {
+        let rule = Rule::Use("t".to_string(), Box::new(formula("P")), vec![], vec![]);
+        let proof = prove("P", vec![rule, Rule::Axiom]);
+
+        let theorems = HashMap::from([("t".to_string(), Box::new(formula("Q")))]);
+        match check(&proof, &theorems) {
+            Err(Error::InvalidProof(msg)) => assert!(msg.ends_with("'t' does not state P"), "{msg}"),
+            r => panic!("expected the proof to be rejected, got {r:?}")
+        }
+        assert!(check(&proof, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn rejects_admitted_goal() {
+        let mut proof = Proof::start(Box::new(formula("P")));
+        proof.admit();
+        assert!(check(&proof, &HashMap::new()).is_err());
+    }
+}
@@ -4,6 +4,13 @@ use crate::error::Error;
 use crate::logic::term::Term;
 use crate::syntax::lexer::Lexer;
 
+/// Return whether the name is the one of a schematic relation variable (like `?A`),
+/// which can be instantiated by any formula when the theorem is used.
+pub fn is_metavariable(name: &str) -> bool {
+    name.starts_with('?')
+}
+
+
 /// First-order logic formula
 #[derive(Debug, Clone, PartialEq, Default)]
 pub enum Formula {
@@ -66,7 +73,8 @@ impl Formula {
     }
 
 
-    /// Replace in this formula a term by another.
+    /// Replace in this formula the free occurrences of a term by another.
+    /// Occurrences in which a variable of `old` is bound by a quantifier are left untouched.
     pub fn rewrite(&mut self, old: &Term, new: &Term) {
         match self {
             Formula::Falsum => {}
@@ -88,8 +96,128 @@ impl Formula {
                 f1.rewrite(old, new);
                 f2.rewrite(old, new);
             },
-            Formula::Forall(_, f)  | Formula::Exists(_, f)=> {
-                f.rewrite(old, new)
+            Formula::Forall(v, f)  | Formula::Exists(v, f)=> {
+                if !old.domain().contains(v) {
+                    f.rewrite(old, new)
+                }
+            }
+        }
+    }
+
+
+    /// Return a copy of this formula where the free occurrences of the variable `v` are replaced by `t`.
+    /// Bound variables are renamed if needed, so that the variables of `t` are not captured.
+    pub fn substitute(&self, v: &str, t: &Term) -> Formula {
+        let sub = |f: &Formula| Box::new(f.substitute(v, t));
+
+        match self {
+            Formula::Falsum => Formula::Falsum,
+            Formula::Relation(r, terms) => Formula::Relation(r.clone(), terms.iter().map(|u| u.substitute(v, t)).collect()),
+            Formula::Not(f) => Formula::Not(sub(f)),
+            Formula::Or(f1, f2) => Formula::Or(sub(f1), sub(f2)),
+            Formula::And(f1, f2) => Formula::And(sub(f1), sub(f2)),
+            Formula::Implies(f1, f2) => Formula::Implies(sub(f1), sub(f2)),
+
+            Formula::Forall(x, f) | Formula::Exists(x, f) => {
+                if x == v || !f.domain().iter().any(|d| d == v) {
+                    return self.clone()
+                }
+
+                // Rename the bound variable if it would capture a variable of t
+                let (x, f) = if t.domain().contains(x) {
+                    let mut used = f.variables();
+                    used.extend(t.domain());
+                    used.push(v.to_string());
+
+                    let fresh = (1..).map(|p| format!("{x}{}", "'".repeat(p)))
+                        .find(|n| !used.contains(n))
+                        .expect("Infinite iterator");
+
+                    let renamed = f.substitute(x, &Term(fresh.clone(), vec![]));
+                    (fresh, renamed)
+                }
+                else {
+                    (x.clone(), f.as_ref().clone())
+                };
+
+                match self {
+                    Formula::Forall(..) => Formula::Forall(x, sub(&f)),
+                    _ => Formula::Exists(x, sub(&f))
+                }
+            }
+        }
+    }
+
+
+    /// Return the formula obtained by instantiating its leading universal quantifiers
+    /// with `terms`, in order.
+    pub fn instantiate(&self, terms: &[Term]) -> Result<Formula, Error> {
+        let mut res = self.clone();
+        for (i, t) in terms.iter().enumerate() {
+            res = match res {
+                Formula::Forall(x, f) => f.substitute(&x, t),
+                _ => return Err(Error::InvalidArguments(format!("{self} has only {i} leading universal quantifier(s)")))
+            }
+        }
+        Ok(res)
+    }
+
+
+    /// Return the schematic relation variables of this formula, in order of appearance
+    pub fn metavariables(&self) -> Vec<String> {
+        match self {
+            Formula::Relation(r, _) if is_metavariable(r) => vec![r.clone()],
+            Formula::Falsum | Formula::Relation(..) => vec![],
+            Formula::Not(f) | Formula::Forall(_, f) | Formula::Exists(_, f) => f.metavariables(),
+            Formula::Or(f1, f2) | Formula::And(f1, f2) | Formula::Implies(f1, f2) => {
+                let mut res = f1.metavariables();
+                res.extend(f2.metavariables().into_iter().filter(|m| !res.contains(m)).collect::<Vec<_>>());
+                res
+            }
+        }
+    }
+
+
+    /// Return a copy of this formula where each schematic relation variable of `values` is
+    /// replaced by its formula, simultaneously.
+    /// Bound variables are renamed if needed, so that the variables of the formulas are not captured.
+    pub fn substitute_relations(&self, values: &[(String, Formula)]) -> Formula {
+        let sub = |f: &Formula| Box::new(f.substitute_relations(values));
+
+        match self {
+            Formula::Relation(r, terms) if terms.is_empty() => match values.iter().find(|(m, _)| m == r) {
+                Some((_, f)) => f.clone(),
+                None => self.clone()
+            },
+            Formula::Falsum | Formula::Relation(..) => self.clone(),
+            Formula::Not(f) => Formula::Not(sub(f)),
+            Formula::Or(f1, f2) => Formula::Or(sub(f1), sub(f2)),
+            Formula::And(f1, f2) => Formula::And(sub(f1), sub(f2)),
+            Formula::Implies(f1, f2) => Formula::Implies(sub(f1), sub(f2)),
+
+            Formula::Forall(x, f) | Formula::Exists(x, f) => {
+                let free = values.iter().flat_map(|(_, g)| g.domain()).collect::<Vec<_>>();
+
+                // Rename the bound variable if it would capture a variable of the formulas
+                let (x, f) = if free.contains(x) {
+                    let mut used = f.variables();
+                    used.extend(free);
+
+                    let fresh = (1..).map(|p| format!("{x}{}", "'".repeat(p)))
+                        .find(|n| !used.contains(n))
+                        .expect("Infinite iterator");
+
+                    let renamed = f.substitute(x, &Term(fresh.clone(), vec![]));
+                    (fresh, renamed)
+                }
+                else {
+                    (x.clone(), f.as_ref().clone())
+                };
+
+                match self {
+                    Formula::Forall(..) => Formula::Forall(x, sub(&f)),
+                    _ => Formula::Exists(x, sub(&f))
+                }
             }
         }
     }
@@ -134,12 +262,36 @@ impl Formula {
     }
 
 
-    /// Return a new variable.
+    /// Return a list of every variable used in this formula, free or bound
+    pub fn variables(&self) -> Vec<String> {
+        let mut res = match self {
+            Formula::Falsum => vec![],
+            Formula::Relation(_, t) => t.iter().flat_map(|t| t.domain()).collect(),
+            Formula::Not(f) => f.variables(),
+            Formula::Or(f1, f2) | Formula::And(f1, f2) | Formula::Implies(f1, f2) => {
+                let mut vars = f1.variables();
+                vars.append(&mut f2.variables());
+                vars
+            }
+            Formula::Exists(v, f) | Formula::Forall(v, f) => {
+                let mut vars = f.variables();
+                vars.push(v.clone());
+                vars
+            }
+        };
+
+        res.sort();
+        res.dedup();
+        res
+    }
+
+
+    /// Return a new variable, not used in this formula (either free or bound).
     /// It will first try variables from x to z then w to a,
     /// then add a ' and repeat until found.
     pub fn new_variable(&self) -> String {
         let mut prime: u8 = 0;
-        let existing = self.domain();
+        let existing = self.variables();
 
         let with_primes = |c: char, p: u8| {
             let mut res = String::new();
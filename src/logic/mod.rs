@@ -3,6 +3,7 @@
 mod term;
 mod formula;
 pub mod rule;
+pub mod unification;
 mod sequent;
 
 pub use formula::*;
@@ -4,10 +4,12 @@ use std::fmt::{Display, Formatter};
 use strum::EnumIter;
 use sculpt_macro::EnumType;
 use crate::error::Error;
+use crate::tools;
 use super::{Formula, Term, Sequent};
 
 
 /// Structure used by some [Rule] variants.
+#[derive(Clone, Debug)]
 pub enum Side {
     Left,
     Right
@@ -29,7 +31,7 @@ impl Default for Side {
 }
 
 
-#[derive(EnumType)]
+#[derive(Clone, Debug, EnumType)]
 pub enum Rule {
     Axiom,
     Intro,
@@ -39,17 +41,50 @@ pub enum Rule {
     And(Side, Box<Formula>),
     Keep(Side),
     FromOr(Box<Formula>),
+    Cut(Option<String>, Box<Formula>),
+    Use(String, Box<Formula>, Vec<(String, Formula)>, Vec<Term>),
 
     Generalize(Box<Term>),
     FixAs(Box<Term>),
     Consider(Box<Formula>),
     RenameAs(String),
 
+    Clear(String),
+    RenameHypothesis(String, String),
+    MoveAfter(String, String),
+    Duplicate(String),
+
 
     FromBottom,
     ExFalso(Box<Formula>)
 }
 
+impl Rule {
+    /// Return a copy of this rule where the free occurrences of the variable `v` in its
+    /// formulas and terms are replaced by `t`
+    pub fn substitute(&self, v: &str, t: &Term) -> Rule {
+        let f = |f: &Formula| Box::new(f.substitute(v, t));
+        match self {
+            Rule::Trans(p) => Rule::Trans(f(p)),
+            Rule::And(side, p) => Rule::And(side.clone(), f(p)),
+            Rule::FromOr(p) => Rule::FromOr(f(p)),
+            Rule::Cut(name, p) => Rule::Cut(name.clone(), f(p)),
+            Rule::Use(name, thm, schema, terms) => Rule::Use(
+                name.clone(),
+                thm.clone(),
+                schema.iter().map(|(m, g)| (m.clone(), g.substitute(v, t))).collect(),
+                terms.iter().map(|u| u.substitute(v, t)).collect()
+            ),
+            Rule::Generalize(u) => Rule::Generalize(Box::new(u.substitute(v, t))),
+            Rule::FixAs(u) => Rule::FixAs(Box::new(u.substitute(v, t))),
+            Rule::Consider(p) => Rule::Consider(f(p)),
+            Rule::ExFalso(p) => Rule::ExFalso(f(p)),
+            r => r.clone()
+        }
+    }
+}
+
+
 impl Display for Rule {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -61,12 +96,29 @@ impl Display for Rule {
             Rule::And(s, _) => write!(f, "And {s}"),
             Rule::Keep(s) => write!(f, "Keep {s}"),
             Rule::FromOr(_) => write!(f, "FromOr"),
+            Rule::Cut(_, s) => write!(f, "Have {s}"),
+            Rule::Use(s, _, schema, terms) => {
+                write!(f, "Use {s}")?;
+                if !schema.is_empty() {
+                    let schema = schema.iter().map(|(m, g)| format!("{} := {g}", m.trim_start_matches('?'))).collect::<Vec<_>>();
+                    write!(f, " [{}]", schema.join(", "))?;
+                }
+                if !terms.is_empty() {
+                    write!(f, " with {}", tools::list_str(terms, ", "))?;
+                }
+                Ok(())
+            }
 
             Rule::Generalize(s) => write!(f, "Generalize {s}"),
             Rule::FixAs(s) => write!(f, "FixAs {s}"),
             Rule::Consider(s) => write!(f, "Consider {s}"),
             Rule::RenameAs(s) => write!(f, "Rename {s}"),
 
+            Rule::Clear(h) => write!(f, "Clear {h}"),
+            Rule::RenameHypothesis(h, k) => write!(f, "Rename {h} into {k}"),
+            Rule::MoveAfter(h, k) => write!(f, "Move {h} after {k}"),
+            Rule::Duplicate(h) => write!(f, "Duplicate {h}"),
+
             Rule::FromBottom => write!(f, "FromBottom"),
             Rule::ExFalso(s) => write!(f, "ExFalso {s}")
         }
@@ -93,12 +145,10 @@ impl Rule {
                 // Intro can be used to introduce predicates or bound variables
                 match sequent.consequent.as_ref() {
                     Formula::Implies(lhs, rhs) => {
-                        let mut antecedents = sequent.antecedents.clone();
-                        antecedents.push(*lhs.to_owned());
-                        
-                        let new_seq = vec![
-                            Sequent::new(antecedents, rhs.to_owned())
-                        ];
+                        let mut with_lhs = Sequent::new(sequent.antecedents.clone(), rhs.to_owned());
+                        with_lhs.push(*lhs.to_owned());
+
+                        let new_seq = vec![with_lhs];
 
                         Ok(new_seq)
                     },
@@ -106,8 +156,9 @@ impl Rule {
 
 
                     Formula::Forall(v, f) => {
+                        // Eigenvariable condition: v must not be free in the antecedents
                         if sequent.domain().contains(v) {
-                            return Err(Error::CommandError(format!("{v} already exists")))
+                            return Err(Error::CommandError(format!("{v} is free in the hypotheses, use 'rename_as' first")))
                         }
 
                         let new_seq = vec![
@@ -172,7 +223,7 @@ impl Rule {
 
 
             Rule::Axiom => {
-                let is_axiom = sequent.antecedents.contains(&sequent.consequent);
+                let is_axiom = sequent.contains(&sequent.consequent);
 
                 if is_axiom { Ok(vec![]) }
                 else { Err(Error::CommandError("Not an axiom".to_string())) }
@@ -222,15 +273,15 @@ impl Rule {
                     _ => return Err(Error::InvalidArguments("Expected a formula in the form P \\/ Q".to_string()))
                 };
 
-                let mut with_prop1 = sequent.antecedents.clone();
-                let mut with_prop2 = sequent.antecedents.clone();
+                let mut with_prop1 = sequent.clone();
+                let mut with_prop2 = sequent.clone();
                 with_prop1.push(*left_prop);
                 with_prop2.push(*right_prop);
 
                 let new_seq = vec![
                     Sequent::new(sequent.antecedents.clone(), or_prop.clone()),
-                    Sequent::new(with_prop1, sequent.consequent.clone()),
-                    Sequent::new(with_prop2, sequent.consequent.clone()),
+                    with_prop1,
+                    with_prop2,
                 ];
 
                 Ok(new_seq)
@@ -238,6 +289,38 @@ impl Rule {
 
 
 
+            Rule::Cut(name, prop) => {
+                let mut with_prop = sequent.clone();
+                match name {
+                    Some(n) => with_prop.push_named(n, *prop.clone())?,
+                    None => { with_prop.push(*prop.clone()); }
+                };
+
+                let new_seq = vec![
+                    Sequent::new(sequent.antecedents.clone(), prop.clone()),
+                    with_prop
+                ];
+
+                Ok(new_seq)
+            }
+
+
+
+
+            Rule::Use(name, thm, schema, terms) => {
+                let instance = thm.substitute_relations(schema).instantiate(terms)?;
+
+                let mut with_thm = sequent.clone();
+                if !with_thm.contains(&instance) {
+                    let name = with_thm.new_name(name);
+                    with_thm.push_named(&name, instance)?;
+                }
+
+                Ok(vec![with_thm])
+            }
+
+
+
 
             Rule::Generalize(term) => {
                 // the term must be present in the formula for it to be generalized
@@ -262,13 +345,10 @@ impl Rule {
                 match sequent.consequent.as_ref() {
 
                     Formula::Exists(exists, formula) => {
-                        if sequent.consequent.exists(term) {return Err(Error::InvalidArguments(format!("{term} already exists")))}
-
-                        let mut fixed = formula.clone();
-                        fixed.rewrite(&Term(exists.clone(), vec![]), term);
+                        let fixed = formula.substitute(exists, term);
 
                         let new_seq = vec![
-                            Sequent::new(sequent.antecedents.clone(), fixed)
+                            Sequent::new(sequent.antecedents.clone(), Box::new(fixed))
                         ];
 
                         Ok(new_seq)
@@ -283,11 +363,11 @@ impl Rule {
             Rule::Consider(new_form) => {
                 match new_form.as_ref() {
                     Formula::Exists(var, nf) => {
-                        if sequent.consequent.domain().contains(var) {return Err(Error::CommandError(format!("{var} already exists in the goal")))}
-                        if sequent.domain().contains(var) {return Err(Error::CommandError(format!("{var} already exists")))}
+                        // Eigenvariable condition: var must not be free in the antecedents or in the goal
+                        if sequent.domain().contains(var) {return Err(Error::CommandError(format!("{var} is free in the sequent")))}
 
                         let mut with_nf = sequent.clone();
-                        with_nf.antecedents.push(*nf.clone());
+                        with_nf.push(*nf.clone());
 
                         let mut goal_nf = sequent.clone();
                         goal_nf.consequent = new_form.clone();
@@ -309,20 +389,17 @@ impl Rule {
             Rule::RenameAs(s) => {
                 match sequent.consequent.as_ref() {
 
-                    Formula::Exists(old, f) => {
-                        let mut nf = Box::new(Formula::Exists(s.clone(), f.clone()));
-                        nf.rewrite(&Term(old.clone(), vec![]), &Term(s.clone(), vec![]));
-                        let mut new_s = sequent.clone();
-                        new_s.consequent = nf;
-
-                        Ok(vec![new_s])
-                    }
+                    Formula::Exists(old, f) | Formula::Forall(old, f) => {
+                        if old != s && sequent.consequent.domain().contains(s) {
+                            return Err(Error::CommandError(format!("{s} is free in the goal")))
+                        }
 
-                    Formula::Forall(old, f) => {
-                        let mut nf = Box::new(Formula::Forall(s.clone(), f.clone()));
-                        nf.rewrite(&Term(old.clone(), vec![]), &Term(s.clone(), vec![]));
+                        let renamed = Box::new(f.substitute(old, &Term(s.clone(), vec![])));
                         let mut new_s = sequent.clone();
-                        new_s.consequent = nf;
+                        new_s.consequent = match sequent.consequent.as_ref() {
+                            Formula::Exists(..) => Box::new(Formula::Exists(s.clone(), renamed)),
+                            _ => Box::new(Formula::Forall(s.clone(), renamed))
+                        };
 
                         Ok(vec![new_s])
                     }
@@ -333,6 +410,57 @@ impl Rule {
 
 
 
+            Rule::Clear(h) => {
+                let mut new_s = sequent.clone();
+                new_s.remove(h)?;
+                Ok(vec![new_s])
+            }
+
+
+
+            Rule::RenameHypothesis(h, k) => {
+                if sequent.get(k).is_some() {
+                    return Err(Error::AlreadyExists(format!("Hypothesis '{k}' already exists")))
+                }
+
+                let mut new_s = sequent.clone();
+                let hyp = new_s.antecedents.iter_mut()
+                    .find(|a| &a.name == h)
+                    .ok_or(Error::InvalidArguments(format!("Unknown hypothesis '{h}'")))?;
+                hyp.name = k.clone();
+
+                Ok(vec![new_s])
+            }
+
+
+
+            Rule::MoveAfter(h, k) => {
+                if h == k {
+                    return Err(Error::InvalidArguments("Cannot move a hypothesis after itself".to_string()))
+                }
+
+                let mut new_s = sequent.clone();
+                let hyp = new_s.remove(h)?;
+                let pos = new_s.antecedents.iter()
+                    .position(|a| &a.name == k)
+                    .ok_or(Error::InvalidArguments(format!("Unknown hypothesis '{k}'")))?;
+                new_s.antecedents.insert(pos + 1, hyp);
+
+                Ok(vec![new_s])
+            }
+
+
+
+            Rule::Duplicate(h) => {
+                let mut new_s = sequent.clone();
+                let hyp = sequent.get(h).ok_or(Error::InvalidArguments(format!("Unknown hypothesis '{h}'")))?;
+                new_s.push(hyp.formula.clone());
+
+                Ok(vec![new_s])
+            }
+
+
+
             Rule::FromBottom => {
                 // invert current formula
                 let new_prop = match sequent.consequent.as_ref() {
@@ -340,12 +468,10 @@ impl Rule {
                     e => Box::new(Formula::Not(Box::new(e.clone())))
                 };
 
-                let mut with_prop = sequent.antecedents.clone();
+                let mut with_prop = Sequent::new(sequent.antecedents.clone(), Box::new(Formula::Falsum));
                 with_prop.push(*new_prop);
 
-                let new_seq = vec![
-                    Sequent::new(with_prop, Box::new(Formula::Falsum))
-                ];
+                let new_seq = vec![with_prop];
 
                 Ok(new_seq)
             }
@@ -388,7 +514,7 @@ impl RuleType {
     pub fn is_applicable(&self, sequent: &Sequent) -> bool {
         match self {
             RuleType::Axiom => {
-                sequent.antecedents.contains(&sequent.consequent)
+                sequent.contains(&sequent.consequent)
             }
             RuleType::Intro | RuleType::Intros => {
                 if let &Formula::Implies(_, _) = &sequent.consequent.as_ref() { true }
@@ -399,6 +525,8 @@ impl RuleType {
             RuleType::And => true,
             RuleType::Keep => matches!(sequent.consequent.as_ref(), &Formula::Or(_, _)),
             RuleType::FromOr => true,
+            RuleType::Cut => true,
+            RuleType::Use => false, // Requires a theorem from the context, see ContextCommand::Use
             RuleType::Generalize => true,
             RuleType::FixAs => matches!(sequent.consequent.as_ref(), &Formula::Exists(_, _)),
             RuleType::Consider => true,
@@ -406,8 +534,51 @@ impl RuleType {
                 if let &Formula::Forall(_, _) = &sequent.consequent.as_ref() { true }
                 else { matches!(sequent.consequent.as_ref(), &Formula::Exists(_, _)) }
             }
+            RuleType::Clear | RuleType::RenameHypothesis | RuleType::Duplicate => !sequent.antecedents.is_empty(),
+            RuleType::MoveAfter => sequent.antecedents.len() > 1,
             RuleType::FromBottom => true,
             RuleType::ExFalso=> matches!(sequent.consequent.as_ref(), &Formula::Falsum)
         }
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::Hypothesis;
+
+    fn relation(name: &str, var: &str) -> Formula {
+        Formula::Relation(name.to_string(), vec![Term(var.to_string(), vec![])])
+    }
+
+    fn consider(var: &str) -> Rule {
+        Rule::Consider(Box::new(Formula::Exists(var.to_string(), Box::new(relation("Q", var)))))
+    }
+
+    #[test]
+    fn consider_rejects_variable_free_in_goal() {
+        let sequent = Sequent::new(vec![], Box::new(relation("P", "y")));
+        match consider("y").apply(&sequent) {
+            Err(Error::CommandError(msg)) => assert_eq!(msg, "y is free in the sequent"),
+            r => panic!("expected the eigenvariable condition to fail, got {r:?}")
+        }
+    }
+
+    #[test]
+    fn consider_rejects_variable_free_in_hypothesis() {
+        let sequent = Sequent::new(vec![Hypothesis::new("H", relation("P", "y"))], Box::new(Formula::Falsum));
+        match consider("y").apply(&sequent) {
+            Err(Error::CommandError(msg)) => assert_eq!(msg, "y is free in the sequent"),
+            r => panic!("expected the eigenvariable condition to fail, got {r:?}")
+        }
+    }
+
+    #[test]
+    fn consider_accepts_fresh_variable() {
+        let sequent = Sequent::new(vec![Hypothesis::new("H", relation("P", "y"))], Box::new(relation("P", "y")));
+        let goals = consider("z").apply(&sequent).expect("z is fresh");
+        assert_eq!(goals.len(), 2);
+        assert!(goals[1].contains(&relation("Q", "z")));
+    }
+}
@@ -1,28 +1,140 @@
 use std::fmt::Display;
 use strum::IntoEnumIterator;
 
-use crate::logic::Formula;
+use crate::error::Error;
+use crate::logic::{Formula, Term};
+use crate::tools;
 use crate::logic::rule::RuleType;
+use crate::logic::unification::alpha_eq;
 
 
-#[derive(Clone, Debug)]
+/// Named antecedent of a [Sequent].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hypothesis {
+    pub name: String,
+    pub formula: Formula
+}
+
+impl Hypothesis {
+    pub fn new(name: &str, formula: Formula) -> Hypothesis {
+        Hypothesis { name: name.to_string(), formula }
+    }
+}
+
+impl Display for Hypothesis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.formula)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Sequent {
-    pub antecedents: Vec<Formula>,
+    pub antecedents: Vec<Hypothesis>,
     pub consequent: Box<Formula>
 }
 
 
 impl Sequent {
-    // Antecedents must be named
-    pub fn new(antecedents: Vec<Formula>, consequent: Box<Formula>) -> Sequent {
+    pub fn new(antecedents: Vec<Hypothesis>, consequent: Box<Formula>) -> Sequent {
         Sequent { antecedents, consequent: consequent.clone() }
     }
 
-    /// Return a list of free variables in this sequent
+    /// Return a list of free variables in this sequent, either in the antecedents or in the consequent
     pub fn domain(&self) -> Vec<String> {
-        self.antecedents.iter()
-            .flat_map(|t| t.domain())
-            .collect()
+        let mut res = self.antecedents.iter()
+            .flat_map(|h| h.formula.domain())
+            .chain(self.consequent.domain())
+            .collect::<Vec<_>>();
+        res.sort();
+        res.dedup();
+        res
+    }
+
+
+    /// Return a copy of this sequent where the free occurrences of the variable `v` are replaced by `t`
+    pub fn substitute(&self, v: &str, t: &Term) -> Sequent {
+        let antecedents = self.antecedents.iter()
+            .map(|h| Hypothesis::new(&h.name, h.formula.substitute(v, t)))
+            .collect();
+        Sequent::new(antecedents, Box::new(self.consequent.substitute(v, t)))
+    }
+
+
+    /// Return the formula `H1 /\ ... /\ Hn => C` equivalent to this sequent
+    pub fn to_formula(&self) -> Formula {
+        let hypotheses = self.antecedents.iter()
+            .map(|h| h.formula.clone())
+            .reduce(|acc, f| Formula::And(Box::new(acc), Box::new(f)));
+
+        match hypotheses {
+            None => *self.consequent.clone(),
+            Some(h) => Formula::Implies(Box::new(h), self.consequent.clone())
+        }
+    }
+
+
+    /// Return whether the formula is one of the antecedents, up to the renaming of bound variables
+    pub fn contains(&self, formula: &Formula) -> bool {
+        self.antecedents.iter().any(|h| alpha_eq(&h.formula, formula))
+    }
+
+
+    /// Return the hypothesis with the given name, if any
+    pub fn get(&self, name: &str) -> Option<&Hypothesis> {
+        self.antecedents.iter().find(|h| h.name == name)
+    }
+
+
+    /// Return a hypothesis name not used in this sequent.
+    /// `base` is used as is if possible, otherwise a number is appended to it.
+    pub fn new_name(&self, base: &str) -> String {
+        if !base.is_empty() && self.get(base).is_none() {
+            return base.to_string()
+        }
+
+        let base = if base.is_empty() { "H" } else { base };
+        (0..).map(|i| format!("{base}{i}"))
+            .find(|n| self.get(n).is_none())
+            .expect("Infinite iterator")
+    }
+
+
+    /// Add a new antecedent, named automatically. Return the name given to the hypothesis.
+    pub fn push(&mut self, formula: Formula) -> String {
+        let name = self.new_name("");
+        self.antecedents.push(Hypothesis::new(&name, formula));
+        name
+    }
+
+
+    /// Add a new antecedent with the given name. Return an Err if the name is already used.
+    pub fn push_named(&mut self, name: &str, formula: Formula) -> Result<(), Error> {
+        if self.get(name).is_some() {
+            return Err(Error::AlreadyExists(format!("Hypothesis '{name}' already exists")))
+        }
+        self.antecedents.push(Hypothesis::new(name, formula));
+        Ok(())
+    }
+
+
+    /// Remove the hypothesis with the given name and return it.
+    pub fn remove(&mut self, name: &str) -> Result<Hypothesis, Error> {
+        match self.antecedents.iter().position(|h| h.name == name) {
+            Some(i) => Ok(self.antecedents.remove(i)),
+            None => Err(Error::InvalidArguments(format!("Unknown hypothesis '{name}'")))
+        }
+    }
+
+
+    /// Return this sequent on a single line, without the names of the hypotheses.
+    pub fn to_inline_string(&self) -> String {
+        let antecedents = self.antecedents.iter().map(|h| h.formula.clone()).collect::<Vec<_>>();
+        if antecedents.is_empty() {
+            format!("⊢ {}", self.consequent)
+        } else {
+            format!("{} ⊢ {}", tools::list_str(&antecedents, ", "), self.consequent)
+        }
     }
 
 
@@ -36,10 +148,10 @@ impl Sequent {
 
 impl Display for Sequent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for formula in &self.antecedents {
-            writeln!(f, "│ {formula}")?;
+        for hypothesis in &self.antecedents {
+            writeln!(f, "│ {hypothesis}")?;
         }
         writeln!(f, "│──────────────────────────")?;
         writeln!(f, "│ {}", self.consequent)
     }
-}
\ No newline at end of file
+}
@@ -49,6 +49,18 @@ impl Term {
 
 
 
+    /// Return a copy of this term where the variable `v` is replaced by `t`.
+    pub fn substitute(&self, v: &str, t: &Term) -> Term {
+        if self.0 == v && self.1.is_empty() {
+            t.clone()
+        }
+        else {
+            Term(self.0.clone(), self.1.iter().map(|u| u.substitute(v, t)).collect())
+        }
+    }
+
+
+
     /// Return a list of each variable in the domain
     /// of this Term.
     pub fn domain(&self) -> Vec<String> {
@@ -0,0 +1,229 @@
+//! First-order unification of formulas, used by the tactics to instantiate theorems.
+//!
+//! Formulas are unified up to the renaming of bound variables. A variable can only be
+//! instantiated by a term which does not use the variables bound in its scope.
+
+use super::{is_metavariable, Formula, Term};
+
+
+/// Instantiation of the variables found by the unification: terms for the term variables,
+/// formulas for the schematic relation variables
+#[derive(Clone, Debug, Default)]
+pub struct Substitution {
+    pub terms: Vec<(String, Term)>,
+    pub formulas: Vec<(String, Formula)>
+}
+
+impl Substitution {
+    /// Return the term instantiating the variable, with the instantiated variables replaced in it
+    pub fn term(&self, v: &str) -> Option<Term> {
+        self.terms.iter().find(|(n, _)| n == v).map(|(_, t)| self.apply_term(t))
+    }
+
+    /// Return the formula instantiating the schematic variable, if any
+    pub fn formula(&self, m: &str) -> Option<&Formula> {
+        self.formulas.iter().find(|(n, _)| n == m).map(|(_, f)| f)
+    }
+
+    /// Replace the instantiated variables in the term
+    pub fn apply_term(&self, t: &Term) -> Term {
+        match self.terms.iter().find(|(n, _)| t.1.is_empty() && n == &t.0) {
+            Some((_, value)) => self.apply_term(value),
+            None => Term(t.0.clone(), t.1.iter().map(|u| self.apply_term(u)).collect())
+        }
+    }
+}
+
+
+/// Unify `pattern` with `target`, extending `subst`.
+/// `variables` are the term variables which can be instantiated, on both sides, and the schematic
+/// relation variables of `pattern` which can be instantiated by formulas.
+/// Return None if the formulas cannot be unified.
+pub fn unify(pattern: &Formula, target: &Formula, variables: &[String], subst: &Substitution) -> Option<Substitution> {
+    let mut unifier = Unifier { variables, bound: vec![], subst: subst.clone() };
+    match unifier.formulas(pattern, target) {
+        true => Some(unifier.subst),
+        false => None
+    }
+}
+
+
+/// Return whether both formulas are equal, up to the renaming of bound variables.
+pub fn alpha_eq(a: &Formula, b: &Formula) -> bool {
+    let mut unifier = Unifier { variables: &[], bound: vec![], subst: Substitution::default() };
+    unifier.formulas(a, b)
+}
+
+
+
+struct Unifier<'a> {
+    variables: &'a [String],
+    bound: Vec<(String, String)>,       // Pairs of variables bound by the enclosing quantifiers, innermost last
+    subst: Substitution
+}
+
+impl Unifier<'_> {
+    fn formulas(&mut self, a: &Formula, b: &Formula) -> bool {
+        match (a, b) {
+            (Formula::Relation(m, args), _) if args.is_empty() && is_metavariable(m) && self.variables.contains(m) => {
+                // The formula must not use the variables bound in its scope
+                if self.bound.iter().any(|(_, y)| b.domain().contains(y)) {
+                    return false
+                }
+                match self.subst.formula(m) {
+                    Some(f) => alpha_eq(&f.clone(), b),
+                    None => {
+                        self.subst.formulas.push((m.clone(), b.clone()));
+                        true
+                    }
+                }
+            }
+
+            (Formula::Falsum, Formula::Falsum) => true,
+            (Formula::Relation(r1, t1), Formula::Relation(r2, t2)) => {
+                r1 == r2 && t1.len() == t2.len() && t1.iter().zip(t2).all(|(u1, u2)| self.terms(u1, u2))
+            }
+            (Formula::Not(f1), Formula::Not(f2)) => self.formulas(f1, f2),
+            (Formula::Or(l1, r1), Formula::Or(l2, r2))
+            | (Formula::And(l1, r1), Formula::And(l2, r2))
+            | (Formula::Implies(l1, r1), Formula::Implies(l2, r2)) => {
+                self.formulas(l1, l2) && self.formulas(r1, r2)
+            }
+            (Formula::Forall(x1, f1), Formula::Forall(x2, f2))
+            | (Formula::Exists(x1, f1), Formula::Exists(x2, f2)) => {
+                self.bound.push((x1.clone(), x2.clone()));
+                let res = self.formulas(f1, f2);
+                self.bound.pop();
+                res
+            }
+            _ => false
+        }
+    }
+
+
+    fn terms(&mut self, a: &Term, b: &Term) -> bool {
+        let (a, b) = (self.walk(a), self.walk(b));
+
+        // Bound variables must be bound by the same quantifier on both sides
+        let bound_a = self.bound.iter().rposition(|(x, _)| a.1.is_empty() && x == &a.0);
+        let bound_b = self.bound.iter().rposition(|(_, y)| b.1.is_empty() && y == &b.0);
+        if bound_a.is_some() || bound_b.is_some() {
+            return bound_a == bound_b
+        }
+
+        match (self.is_variable(&a), self.is_variable(&b)) {
+            (true, true) if a.0 == b.0 => true,
+            (true, _) => self.bind(&a.0, &b),
+            (_, true) => self.bind(&b.0, &a),
+            _ => a.0 == b.0 && a.1.len() == b.1.len() && a.1.iter().zip(&b.1).all(|(u1, u2)| self.terms(u1, u2))
+        }
+    }
+
+
+    /// Instantiate the variable with the term, if the term does not use it (occurs check)
+    /// nor a bound variable
+    fn bind(&mut self, v: &str, t: &Term) -> bool {
+        let domain = self.subst.apply_term(t).domain();
+        if domain.iter().any(|d| d == v || self.bound.iter().any(|(x, y)| d == x || d == y)) {
+            return false
+        }
+        self.subst.terms.push((v.to_string(), t.clone()));
+        true
+    }
+
+
+    fn is_variable(&self, t: &Term) -> bool {
+        t.1.is_empty() && self.variables.contains(&t.0)
+    }
+
+
+    /// Replace an instantiated variable by its term, until the term is not an instantiated variable
+    fn walk(&self, t: &Term) -> Term {
+        let mut t = t.clone();
+        while let Some((_, value)) = self.subst.terms.iter().find(|(n, _)| self.is_variable(&t) && n == &t.0) {
+            t = value.clone();
+        }
+        t
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::lexer::Lexer;
+
+    fn formula(s: &str) -> Formula {
+        Formula::parse(&mut Lexer::from(s)).expect("valid formula")
+    }
+
+    fn term(s: &str) -> Term {
+        Term::parse(&mut Lexer::from(s)).expect("valid term")
+    }
+
+    fn unify_str(pattern: &str, target: &str, variables: &[&str]) -> Option<Substitution> {
+        let variables = variables.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        unify(&formula(pattern), &formula(target), &variables, &Substitution::default())
+    }
+
+    #[test]
+    fn unify_instantiates_variables() {
+        let s = unify_str("R(x, b)", "R(f(a), y)", &["x", "y"]).expect("unifiable");
+        assert_eq!(s.term("x"), Some(term("f(a)")));
+        assert_eq!(s.term("y"), Some(term("b")));
+
+        let s = unify_str("R(x, x)", "R(y, g(a))", &["x", "y"]).expect("unifiable");
+        assert_eq!(s.term("x"), Some(term("g(a)")));
+        assert_eq!(s.term("y"), Some(term("g(a)")));
+    }
+
+    #[test]
+    fn unify_is_consistent() {
+        assert!(unify_str("R(x, x)", "R(a, a)", &["x"]).is_some());
+        assert!(unify_str("R(x, x)", "R(a, b)", &["x"]).is_none());
+        assert!(unify_str("P(x)", "Q(x)", &["x"]).is_none());
+        assert!(unify_str("P(a)", "P(b)", &[]).is_none());
+        assert!(unify_str("P(f(x))", "P(g(a))", &["x"]).is_none());
+    }
+
+    #[test]
+    fn unify_occurs_check() {
+        assert!(unify_str("P(x)", "P(f(x))", &["x"]).is_none());
+        assert!(unify_str("R(x, x)", "R(y, f(y))", &["x", "y"]).is_none());
+        assert!(unify_str("R(x, f(y))", "R(y, x)", &["x", "y"]).is_none());
+    }
+
+    #[test]
+    fn unify_bound_variables() {
+        // A variable cannot be instantiated by a variable bound in its scope
+        assert!(unify_str("forall y, P(x)", "forall z, P(z)", &["x"]).is_none());
+        assert!(unify_str("forall y, P(y)", "forall z, P(z)", &["x"]).is_some());
+        assert!(unify_str("forall y, R(x, y)", "forall z, R(a, z)", &["x"]).is_some());
+        // Bound variables must be bound by the same quantifier
+        assert!(unify_str("forall y, forall z, R(y, z)", "forall z, forall y, R(y, z)", &[]).is_none());
+        assert!(unify_str("forall y, P(y)", "exists y, P(y)", &[]).is_none());
+    }
+
+    #[test]
+    fn unify_schematic_variables() {
+        let s = unify_str("?A => ?A", "P(a) => P(a)", &["?A"]).expect("unifiable");
+        assert_eq!(s.formula("?A"), Some(&formula("P(a)")));
+        assert!(unify_str("?A => ?A", "P(a) => P(b)", &["?A"]).is_none());
+        assert!(unify_str("forall x, ?A", "forall y, P(y)", &["?A"]).is_none());
+        assert!(unify_str("forall x, ?A", "forall y, P(a)", &["?A"]).is_some());
+        // Not instantiable
+        assert!(unify_str("?A", "P", &[]).is_none());
+    }
+
+    #[test]
+    fn alpha_equivalence() {
+        assert!(alpha_eq(&formula("forall x, exists y, R(x, y)"), &formula("forall y, exists x, R(y, x)")));
+        assert!(alpha_eq(&formula("forall x, forall x, P(x)"), &formula("forall y, forall z, P(z)")));
+        assert!(!alpha_eq(&formula("forall x, forall x, P(x)"), &formula("forall y, forall z, P(y)")));
+        assert!(!alpha_eq(&formula("forall x, exists y, R(x, y)"), &formula("forall y, exists y, R(y, y)")));
+        // Free variables are not renamed
+        assert!(!alpha_eq(&formula("forall x, P(y)"), &formula("forall y, P(y)")));
+        assert!(!alpha_eq(&formula("P(x)"), &formula("P(y)")));
+    }
+}
@@ -1,4 +1,8 @@
 mod proof;
+mod kernel;
+mod tactics;
+mod export;
+mod tptp;
 mod tools;
 mod logic;
 mod engine;
@@ -13,6 +17,8 @@ use repl::Repl;
 
 #[cfg(feature = "exec")]
 use exec::Executor;
+#[cfg(feature = "exec")]
+use export::ExportFormat;
 
 
 
@@ -24,14 +30,25 @@ fn start_repl() -> Result<(), Error> {
 }
 
 #[cfg(feature = "exec")]
-fn exec_file(filename: String) {
+fn exec_file(filename: String, export: Option<(ExportFormat, String)>) {
     let mut exec = Executor::from_file(filename).unwrap();
 
     match exec.exec_all() {
         Ok(_) => {}
         Err(e) => {
             println!("ERROR: {}", e.0);
-            println!("  from {:?} to {:?}", e.1.start, e.1.end)
+            println!("  from {:?} to {:?}", e.1.start, e.1.end);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some((format, output)) = export {
+        let res = exec.export(format)
+            .and_then(|content| std::fs::write(&output, content).map_err(|_| Error::CommandError(format!("Unable to write {output}"))));
+
+        if let Err(e) = res {
+            println!("ERROR: {e}");
+            std::process::exit(1);
         }
     }
 }
@@ -63,12 +80,26 @@ fn main() {
         }
     }
     else if &args[1] == "exec" {
-        if args.len() == 2 {
-            println!("ERROR: Expected a file name");
-            std::process::exit(1);
-        }
-        else {
-            exec_file(args[2].clone())
+        // sculpt exec <file> [--export <format> <output>]
+        match &args[2..] {
+            [file] => exec_file(file.clone(), None),
+            [file, opt, format, output] if opt == "--export" => {
+                match ExportFormat::from_name(format) {
+                    Ok(f) => exec_file(file.clone(), Some((f, output.clone()))),
+                    Err(e) => {
+                        println!("ERROR: {e}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            [] => {
+                println!("ERROR: Expected a file name");
+                std::process::exit(1);
+            }
+            _ => {
+                println!("ERROR: Usage: sculpt exec <file> [--export <format> <output>]");
+                std::process::exit(1);
+            }
         }
     }
 }
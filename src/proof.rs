@@ -1,14 +1,41 @@
 use std::collections::VecDeque;
 use crate::error::Error;
-use crate::logic::{Formula, Sequent};
+use crate::logic::{is_metavariable, Formula, Sequent, Term};
+use crate::logic::unification::{unify, Substitution};
 use crate::logic::rule::{Rule, RuleType};
 
 
+/// Node of the derivation tree built by a [Proof]: a sequent, the rule applied to it
+/// and the sequents this rule produced.
+#[derive(Clone, Debug)]
+pub struct ProofNode {
+    pub sequent: Sequent,
+    pub rule: Option<Rule>,     // None while the goal is open
+    pub children: Vec<usize>,
+    pub admitted: bool          // Open goal considered proven using 'Admit'
+}
+
+impl ProofNode {
+    fn new(sequent: Sequent) -> ProofNode {
+        ProofNode { sequent, rule: None, children: vec![], admitted: false }
+    }
+
+    /// Return whether no rule was applied to this node yet.
+    pub fn is_open(&self) -> bool {
+        self.rule.is_none() && !self.admitted
+    }
+}
+
+
+/// Derivation of a formula, stored as a tree of [ProofNode].
+/// The root of the tree (index 0) is the sequent ⊢ goal. The leaves that
+/// are not yet proven are the goals, the first one being the current goal.
 #[derive(Clone, Debug)]
 pub struct Proof {
     pub goal: Formula,
-    current_goal: Option<Box<Sequent>>,
-    sub_goals: VecDeque<Box<Sequent>>,
+    nodes: Vec<ProofNode>,
+    goals: VecDeque<usize>,
+    metavariables: Vec<(String, Vec<usize>)>,   // Unsolved metavariables, with the nodes they were introduced at
     pub step: u16
 }
 
@@ -20,8 +47,9 @@ impl Proof {
 
         Proof {
             goal: *goal.clone(),
-            current_goal: Some(Box::new(goal_seq)),
-            sub_goals: VecDeque::new(),
+            nodes: vec![ProofNode::new(goal_seq)],
+            goals: VecDeque::from([0]),
+            metavariables: vec![],
             step: 0
         }
     }
@@ -39,43 +67,221 @@ impl Proof {
     }*/
 
 
-    pub fn add_antecedent(&mut self, ante: Box<Formula>) -> Result<(), Error> {
-        match self.current_goal {
-            None => Err(Error::CommandError("Proof is finished".to_string())),
-            Some(ref mut cg) => {
-                if !cg.antecedents.contains(&ante) {
-                    cg.antecedents.push(*ante);
+    /// Add a theorem to the antecedents of the current goal, named after the theorem if possible.
+    pub fn add_antecedent(&mut self, name: &str, ante: Box<Formula>, schema: Vec<(String, Formula)>, terms: Vec<Term>) -> Result<(), Error> {
+        if self.is_finished() {
+            return Err(Error::CommandError("Proof is finished".to_string()))
+        }
+        self.apply(Rule::Use(name.to_string(), ante, schema, terms))
+    }
+
+
+    pub fn apply(&mut self, rule: Rule) -> Result<(), Error> {
+        match rule {
+            // The metavariables introduced by 'fix_as' can be solved later
+            Rule::FixAs(ref t) => {
+                let node = *self.goals.front().ok_or(Error::InvalidCommand("Proof finished".to_string()))?;
+                let metavariables = t.domain().into_iter().filter(|v| is_metavariable(v)).collect::<Vec<_>>();
+
+                self.apply_to_current(rule)?;
+                for m in metavariables {
+                    match self.metavariables.iter_mut().find(|(n, _)| n == &m) {
+                        Some((_, nodes)) => nodes.push(node),
+                        None => self.metavariables.push((m, vec![node]))
+                    }
                 }
-                Ok(())
             }
+            // The goal is unified with a hypothesis, solving metavariables if needed
+            Rule::Axiom if !self.metavariables.is_empty() => {
+                let goal = self.current_goal().ok_or(Error::InvalidCommand("Proof finished".to_string()))?;
+                if !goal.contains(&goal.consequent) {
+                    let variables = self.metavariables();
+                    let solved = goal.antecedents.iter()
+                        .filter_map(|h| unify(&h.formula, &goal.consequent, &variables, &Substitution::default()))
+                        .find_map(|s| {
+                            let mut res = self.clone();
+                            res.instantiate(&s).ok().map(|_| res)
+                        });
+                    if let Some(res) = solved {
+                        *self = res;
+                    }
+                }
+                self.apply_to_current(rule)?
+            }
+            // 'intros' is stored as a sequence of 'intro'
+            Rule::Intros => {
+                self.apply_to_current(Rule::Intro)?;
+                while self.current_goal().is_some_and(|g| RuleType::Intro.is_applicable(g))
+                    && self.apply_to_current(Rule::Intro).is_ok() {}
+            }
+            _ => self.apply_to_current(rule)?
         }
+
+        self.step += 1;
+
+        Ok(())
     }
 
 
-    pub fn apply(&mut self, rule: Rule) -> Result<(), Error> {
-        let crrt_goal = match &self.current_goal {
+    /// Apply the rule to the current goal, and replace it with the newly created goals.
+    fn apply_to_current(&mut self, rule: Rule) -> Result<(), Error> {
+        let current = match self.goals.front() {
             None => return Err(Error::InvalidCommand("Proof finished".to_string())),
-            Some(seq) => seq
+            Some(&id) => id
         };
 
-        let res = rule.apply(crrt_goal)?;
+        let res = rule.apply(&self.nodes[current].sequent)?;
 
-        //self.previous_state = Some(Box::new(self.clone())); // Allow undo operation
+        let first_child = self.nodes.len();
+        self.nodes.extend(res.into_iter().map(ProofNode::new));
+        let children = (first_child..self.nodes.len()).collect::<Vec<_>>();
 
-        for new_seq in res.into_iter().rev() {
-            self.sub_goals.insert(0, Box::new(new_seq))
+        self.goals.pop_front();
+        for c in children.iter().rev() {
+            self.goals.push_front(*c);
         }
 
-        self.current_goal = self.sub_goals.pop_front();
-        self.step += 1;
+        let node = &mut self.nodes[current];
+        node.rule = Some(rule);
+        node.children = children;
+
+        Ok(())
+    }
+
+
+    /// Return the metavariables introduced by 'fix_as' which are not solved yet
+    pub fn metavariables(&self) -> Vec<String> {
+        self.metavariables.iter().map(|(m, _)| m.clone()).collect()
+    }
+
+
+    /// Replace the metavariables solved by the substitution in the whole derivation.
+    /// A metavariable cannot be instantiated by a term using an eigenvariable introduced after it.
+    pub fn instantiate(&mut self, subst: &Substitution) -> Result<(), Error> {
+        let solved = self.metavariables.iter()
+            .filter_map(|(m, nodes)| subst.term(m).map(|t| (m.clone(), nodes.clone(), t)))
+            .collect::<Vec<_>>();
+
+        for (m, nodes, t) in &solved {
+            let introduced = nodes.iter().flat_map(|&n| self.eigenvariables(n)).collect::<Vec<_>>();
+            if let Some(v) = t.domain().iter().find(|v| introduced.contains(v)) {
+                return Err(Error::CommandError(format!("{m} cannot be instantiated by {t}, as {v} is introduced after it")))
+            }
+        }
+
+        for (m, nodes, t) in solved {
+            for node in &mut self.nodes {
+                node.sequent = node.sequent.substitute(&m, &t);
+                node.rule = node.rule.as_ref().map(|r| r.substitute(&m, &t));
+            }
+            self.metavariables.retain(|(n, _)| n != &m);
+
+            // The metavariables used by the term inherit the constraints of the solved one
+            for (n, others) in &mut self.metavariables {
+                if t.domain().contains(n) {
+                    others.extend(nodes.iter().copied());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Return the eigenvariables introduced in the subtree of the node
+    fn eigenvariables(&self, id: usize) -> Vec<String> {
+        let node = &self.nodes[id];
+        let mut res = match (&node.rule, node.sequent.consequent.as_ref()) {
+            (Some(Rule::Intro), Formula::Forall(v, _)) => vec![v.clone()],
+            (Some(Rule::Consider(ex)), _) => match ex.as_ref() {
+                Formula::Exists(v, _) => vec![v.clone()],
+                _ => vec![]
+            },
+            _ => vec![]
+        };
+        res.extend(node.children.iter().flat_map(|&c| self.eigenvariables(c)));
+        res
+    }
+
+
+    /// Consider every remaining goal proven.
+    pub fn admit(&mut self) {
+        for id in self.goals.drain(..) {
+            self.nodes[id].admitted = true;
+        }
+    }
+
 
+
+    /// Return an Err if there is no goal numbered n (goals are numbered from 1)
+    fn expect_goal(&self, n: usize) -> Result<(), Error> {
+        if n == 0 || n > self.remaining_goals_nb() {
+            Err(Error::InvalidArguments(format!("No goal numbered {n}")))
+        } else {
+            Ok(())
+        }
+    }
+
+
+    /// Make the n-th goal the current one
+    pub fn focus(&mut self, n: usize) -> Result<(), Error> {
+        self.expect_goal(n)?;
+        let goal = self.goals.remove(n - 1).unwrap();
+        self.goals.push_front(goal);
         Ok(())
     }
 
 
+    /// Exchange the first two goals
+    pub fn swap(&mut self) -> Result<(), Error> {
+        self.expect_goal(2)?;
+        self.goals.swap(0, 1);
+        Ok(())
+    }
+
+
+    /// Move the first n goals to the end of the goal list
+    pub fn rotate(&mut self, n: usize) -> Result<(), Error> {
+        self.expect_goal(1)?;
+        let len = self.goals.len();
+        self.goals.rotate_left(n % len);
+        Ok(())
+    }
+
+
+    /// Move the n-th goal to the end of the goal list
+    pub fn defer(&mut self, n: usize) -> Result<(), Error> {
+        self.expect_goal(n)?;
+        let goal = self.goals.remove(n - 1).unwrap();
+        self.goals.push_back(goal);
+        Ok(())
+    }
+
+
+    pub fn current_goal(&self) -> Option<&Sequent> {
+        self.goals.front().map(|&id| &self.nodes[id].sequent)
+    }
+
+
+    /// Return the root of the derivation tree, i.e the node of the sequent ⊢ goal.
+    pub fn root(&self) -> &ProofNode {
+        &self.nodes[0]
+    }
+
+    /// Return the node of the derivation tree with the given index.
+    pub fn node(&self, id: usize) -> &ProofNode {
+        &self.nodes[id]
+    }
+
+
+    /// Return the number (starting from 1) of the goal at the given node, if it is still to be proven.
+    pub fn goal_number(&self, id: usize) -> Option<usize> {
+        self.goals.iter().position(|&g| g == id).map(|n| n + 1)
+    }
+
 
     pub fn get_applicable_rules(&self) -> Option<Vec<RuleType>> {
-        Some(self.current_goal.clone()?.get_applicable_rules())
+        Some(self.current_goal()?.get_applicable_rules())
     }
 
 
@@ -94,22 +300,87 @@ impl Proof {
         };
 
         println!("│");
-        
-        match &self.current_goal {
-            Some(cg) => println!("{}", cg),
-            None => {
-                println!("│──────────────────────────");
-                println!("│ (no more goals)")
-            },
+        println!("{}", self.goals_to_string(false));
+    }
+
+
+    /// Return the numbered list of the remaining goals.
+    /// The current goal is always shown in full. If `full` is false, only the consequent
+    /// of the other goals is shown.
+    pub fn goals_to_string(&self, full: bool) -> String {
+        if self.is_finished() {
+            return "│ (no more goals)\n".to_string();
+        }
+
+        let total = self.remaining_goals_nb();
+        let goals = self.goals.iter().map(|&id| &self.nodes[id].sequent);
+
+        let mut res = String::new();
+        for (i, goal) in goals.enumerate() {
+            if i == 0 || full {
+                if i > 0 { res.push_str("│\n"); }
+                res.push_str(&format!("│ Goal {}/{total}\n│\n{goal}", i + 1));
+            }
+            else {
+                if i == 1 { res.push_str("│\n"); }
+                res.push_str(&format!("│ Goal {}/{total}: {}\n", i + 1, goal.consequent));
+            }
+        }
+
+        res
+    }
+
+
+    /// Return the derivation tree, one sequent per line, each followed by the rule applied to it.
+    pub fn tree_to_string(&self) -> String {
+        let mut res = String::new();
+        self.write_subtree(0, "", "", &mut res);
+        res
+    }
+
+    fn write_subtree(&self, id: usize, prefix: &str, children_prefix: &str, res: &mut String) {
+        let node = &self.nodes[id];
+
+        let status = match &node.rule {
+            Some(r) => r.to_string(),
+            None if node.is_open() => {
+                let n = self.goal_number(id).expect("Open node is not a goal");
+                format!("goal {n}")
+            }
+            None => "admitted".to_string()
+        };
+        res.push_str(&format!("{prefix}{}    [{status}]\n", node.sequent.to_inline_string()));
+
+        for (i, &child) in node.children.iter().enumerate() {
+            let (p, cp) = if i + 1 == node.children.len() { ("└─ ", "   ") } else { ("├─ ", "│  ") };
+            self.write_subtree(child, &format!("{children_prefix}{p}"), &format!("{children_prefix}{cp}"), res);
+        }
+    }
+
+
+    /// Return the theorems used in the derivation, without duplicates
+    pub fn dependencies(&self) -> Vec<String> {
+        let mut res = vec![];
+        for n in &self.nodes {
+            if let Some(Rule::Use(thm, ..)) = &n.rule {
+                if !res.contains(thm) { res.push(thm.clone()) }
+            }
         }
+        res
+    }
+
+
+    /// Return whether a part of the derivation was admitted
+    pub fn is_admitted(&self) -> bool {
+        self.nodes.iter().any(|n| n.admitted)
     }
 
 
     pub fn is_finished(&self) -> bool {
-        self.current_goal.is_none()
+        self.goals.is_empty()
     }
 
     pub fn remaining_goals_nb(&self) -> usize {
-        self.sub_goals.len() + 1
+        self.goals.len()
     }
-}
\ No newline at end of file
+}
@@ -24,8 +24,10 @@ pub enum ReplCommand {
     HelpCommand(String),
     #[cmd(name="list", desc="Display the list of commands for the current context")]
     List,
-    #[cmd(name="undo", desc="Revert last command while in proof mode")]
-    Undo,
+    #[cmd(name="export", usage="<format> [thm_name]", desc="Write the proofs of the context (or of a theorem) to a file. Formats: bussproofs, fitch, fitch-latex, dot, tptp, smtlib, lean, coq")]
+    Export(String, Option<String>),
+    #[cmd(name="import", usage="<name>", desc="Read the TPTP problem <name>.p: add its axioms to the context and start proving its conjecture")]
+    Import(String),
     #[cmd(name="exit", desc="Close sub-screens (help, list) or go back to main screen")]
     Exit,
     #[cmd(name="quit", desc="Stop deducnat")]
@@ -40,7 +42,9 @@ impl Display for ReplCommand {
             ReplCommand::Help => write!(f, "help"),
             ReplCommand::HelpCommand(s) => write!(f, "help {s}"),
             ReplCommand::List => write!(f, "list"),
-            ReplCommand::Undo => write!(f, "undo"),
+            ReplCommand::Export(format, None) => write!(f, "export {format}"),
+            ReplCommand::Export(format, Some(thm)) => write!(f, "export {format} {thm}"),
+            ReplCommand::Import(name) => write!(f, "import {name}"),
             ReplCommand::Exit => write!(f, "exit"),
             ReplCommand::Quit => write!(f, "quit"),
             ReplCommand::Return => write!(f, ""),
@@ -103,9 +107,19 @@ impl Command {
                 return Err(Error::TooMuchArguments(cname.to_string()))
             }
 
-            ("undo", s) if s.is_empty() => Command::ReplCommand(ReplCommand::Undo),
-            ("undo", s) if !s.is_empty() => {
-                return Err(Error::TooMuchArguments(cname.to_string()))
+            ("export", s) => {
+                let args = s.split_whitespace().map(|a| a.to_string()).collect::<Vec<_>>();
+                match args.as_slice() {
+                    [] => return Err(Error::ArgumentsRequired("Expected an export format".to_string())),
+                    [format] => Command::ReplCommand(ReplCommand::Export(format.clone(), None)),
+                    [format, thm] => Command::ReplCommand(ReplCommand::Export(format.clone(), Some(thm.clone()))),
+                    _ => return Err(Error::TooMuchArguments(cname.to_string()))
+                }
+            }
+
+            ("import", s) if !s.is_empty() => Command::ReplCommand(ReplCommand::Import(s)),
+            ("import", s) if s.is_empty() => {
+                return Err(Error::ArgumentsRequired("Expected a file name, without extension".to_string()))
             }
 
             ("exit", s) if s.is_empty() => Command::ReplCommand(ReplCommand::Exit),
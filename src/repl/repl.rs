@@ -1,12 +1,14 @@
 use std::cmp::max;
-use std::io;
+use std::{fs, io};
 use std::io::Write;
 use crossterm::cursor::MoveTo;
 use crossterm::{execute, terminal};
 use strum::IntoEnumIterator;
 use unicode_segmentation::UnicodeSegmentation;
 use crate::error::Error;
-use crate::engine::{Engine, ContextCommand, RuleCommand, EngineCommand};
+use crate::export::ExportFormat;
+use crate::tptp;
+use crate::engine::{Engine, ContextCommand, RuleCommand, EngineCommand, EngineEffect};
 use crate::repl::command::{Command, ReplCommand, ReplCommandReplDoc};
 use crate::tools::{self, ColumnJustification};
 
@@ -28,10 +30,11 @@ macro_rules! titleline {
 #[derive(Clone)]
 pub enum ReplState {
     Idle,
-    Working(Box<Engine>, Box<ReplState>),
+    Working(Box<Engine>),
     Help(Box<ReplState>),
     CommandHelp(Command, Box<ReplState>),
     CommandStack(Box<ReplState>),
+    Output(String, Box<ReplState>),
     Quitting,
 }
 
@@ -73,13 +76,14 @@ impl Repl {
             ReplState::Idle => {
                 res.push(Command::ReplCommand(ReplCommand::Context("".to_string())));
             }
-            ReplState::Working(inter, _) => {
+            ReplState::Working(inter) => {
                 res.extend(inter.get_valid_commands()
                     .iter()
                     .map(|cmd| Command::EngineCommand(cmd.clone()))
                     .collect::<Vec<_>>()
                 );
-                res.push(Command::ReplCommand(ReplCommand::Undo));
+                res.push(Command::ReplCommand(ReplCommand::Export("".to_string(), None)));
+                res.push(Command::ReplCommand(ReplCommand::Import("".to_string())));
                 res.push(Command::ReplCommand(ReplCommand::Exit));
                 res.push(Command::ReplCommand(ReplCommand::Help));
             }
@@ -92,6 +96,9 @@ impl Repl {
             ReplState::CommandStack(_) => {
                 res.push(Command::ReplCommand(ReplCommand::Exit));
             }
+            ReplState::Output(_, _) => {
+                res.push(Command::ReplCommand(ReplCommand::Exit));
+            }
             ReplState::Quitting => ()
         };
 
@@ -229,7 +236,7 @@ impl Repl {
                 }
             }
 
-            ReplState::Working(ref mut ctx, _) => {
+            ReplState::Working(ref mut ctx) => {
                 titleline!(ctx.name);
                 match &ctx.current_proof {
                     None => {
@@ -252,7 +259,7 @@ impl Repl {
 
             ReplState::CommandStack(state) => {
                 let p = match state.as_ref() {
-                    ReplState::Working(p, _) => p,
+                    ReplState::Working(p) => p,
                     _ => unreachable!()
                 };
 
@@ -276,6 +283,16 @@ impl Repl {
                 println!("{cols}");
             }
 
+            ReplState::Output(text, state) => {
+                if let ReplState::Working(p) = state.as_ref() {
+                    titleline!(p.name);
+                } else {
+                    titleline!();
+                }
+                println!();
+                println!("{text}");
+            }
+
             ReplState::Quitting => {}
         }
 
@@ -329,16 +346,44 @@ impl Repl {
                     // Start context
                     (ReplState::Idle, ReplCommand::Context(s)) => {
                         let ctx = Engine::new(s);
-                        self.state = ReplState::Working(Box::new(ctx), Box::new(ReplState::Idle));
+                        self.state = ReplState::Working(Box::new(ctx));
                     }
 
-                    // Revert operation
-                    (ReplState::Working(_, prev), ReplCommand::Undo) => {
-                        self.state = *prev.clone();
+                    // Export proofs to a file
+                    (ReplState::Working(engine), ReplCommand::Export(format, theorem)) => {
+                        let format = ExportFormat::from_name(&format)?;
+                        let content = engine.export(format, theorem.as_deref())?;
+
+                        let filename = format!("{}.{}", theorem.as_ref().unwrap_or(&engine.name), format.extension());
+                        fs::write(&filename, content).map_err(|_| Error::CommandError(format!("Unable to write {filename}")))?;
+
+                        self.state = ReplState::Output(format!("Exported to {filename}"), Box::new(curr_clone));
+                    }
+
+                    // Read a TPTP problem. Either every command succeeds, or the context is left untouched
+                    (ReplState::Working(engine), ReplCommand::Import(name)) => {
+                        let filename = format!("{name}.p");
+                        let content = fs::read_to_string(&filename).map_err(|_| Error::CommandError(format!("Unable to read {filename}")))?;
+
+                        let mut new_engine = engine.clone();
+                        let commands = tptp::read(&content)?;
+                        for cmd in commands.iter().cloned() {
+                            new_engine.execute(cmd)?;
+                        }
+
+                        let axioms = commands.iter()
+                            .filter(|c| matches!(c, EngineCommand::ContextCommand(ContextCommand::Axiom(..))))
+                            .count();
+                        let msg = match new_engine.current_proof.as_ref() {
+                            Some((thm, _)) => format!("Imported {axioms} axiom(s) from {filename}, proving {thm}"),
+                            None => format!("Imported {axioms} axiom(s) from {filename}")
+                        };
+
+                        self.state = ReplState::Output(msg, Box::new(ReplState::Working(new_engine)));
                     }
 
                     // Go to command stack display
-                    (ReplState::Working(_, _), ReplCommand::List) => {
+                    (ReplState::Working(_), ReplCommand::List) => {
                         self.state = ReplState::CommandStack(Box::new(curr_clone));
                     }
 
@@ -370,6 +415,11 @@ impl Repl {
                         self.state = *s.clone();
                     }
 
+                    // Exit command output
+                    (ReplState::Output(_, s), ReplCommand::Exit | ReplCommand::Return) => {
+                        self.state = *s.clone();
+                    }
+
                     // 'Return' only has an effect on sub-screens (Help, CommandHelp, CommandStack))
                     (_, ReplCommand::Return) => (),
 
@@ -381,9 +431,20 @@ impl Repl {
 
 
             // Other commands (that could be found in a file for example)
-            (ReplState::Working(ref mut inter, ref mut prev), Command::EngineCommand(cmd)) => {
-                inter.execute(cmd)?;
-                *prev = Box::new(curr_clone);
+            (ReplState::Working(ref mut inter), Command::EngineCommand(cmd)) => {
+                let effects = inter.execute(cmd)?;
+
+                // Some commands (Show Goals, ...) produce text displayed on its own screen
+                let output = effects.into_iter()
+                    .filter_map(|e| match e {
+                        EngineEffect::Info(s) => Some(s),
+                        _ => None
+                    })
+                    .collect::<Vec<_>>();
+
+                if !output.is_empty() {
+                    self.state = ReplState::Output(output.join("\n"), Box::new(self.state.clone()));
+                }
             }
 
             (_, cmd) => {
@@ -8,7 +8,7 @@
 use std::fmt::{Debug, Display, Formatter};
 use std::str::CharIndices;
 
-const SYMBOLS: [&str; 9] = [
+const SYMBOLS: [&str; 16] = [
     "~",
     "=>",
     "\\/",
@@ -17,7 +17,14 @@ const SYMBOLS: [&str; 9] = [
     ")",
     ",",
     ".",
-    "::"
+    "::",
+    ":=",
+    "?",
+    "[",
+    "]",
+    ";",
+    "|",
+    ":"
 ];
 
 
@@ -69,7 +76,14 @@ pub enum Token {
     CloseParen,         // )
     Comma,              // ,
     Dot,                // .
-    DoubleColon         // ::
+    DoubleColon,        // ::
+    ColonEqual,         // :=
+    Question,           // ?
+    OpenBracket,        // [
+    CloseBracket,       // ]
+    Semicolon,          // ;
+    Bar,                // |
+    Colon               // :
 }
 
 impl Display for Token {
@@ -93,6 +107,13 @@ impl Display for Token {
             Token::Comma => ",",
             Token::Dot => ".",
             Token::DoubleColon => "::",
+            Token::ColonEqual => ":=",
+            Token::Question => "?",
+            Token::OpenBracket => "[",
+            Token::CloseBracket => "]",
+            Token::Semicolon => ";",
+            Token::Bar => "|",
+            Token::Colon => ":",
         };
         write!(f, "{str}")
     }
@@ -109,6 +130,7 @@ enum BufState {
 pub struct Lexer<'input> {
     //pub tokens: Vec<Spanned<Token, usize>>,
 
+    source: &'input str,
     iterator: CharIndices<'input>,
     buf_state: BufState,
     buf: String,
@@ -120,6 +142,7 @@ pub struct Lexer<'input> {
 impl<'input> Lexer<'input> {
     pub fn from(s: &'input str) -> Self {
         Self {
+            source: s,
             iterator: s.char_indices(),
             buf_state: BufState::Idle,
             buf: String::new(),
@@ -140,6 +163,37 @@ impl<'input> Lexer<'input> {
         }
     }
 
+    /// Consume tokens until one matching `pred` is found outside of parentheses and brackets.
+    /// Return the source text read before this token, and the token itself (which is consumed),
+    /// or the rest of the source and None if no such token was found.
+    pub fn read_until<P: Fn(&Token) -> bool>(&mut self, pred: P) -> Result<(&'input str, Option<Token>), LexicalError> {
+        let mut start = None;
+        let mut depth = 0;
+
+        while let Some(r) = self.next() {
+            let (s, t, _) = r?;
+            if depth == 0 && pred(&t) {
+                let text = start.map_or("", |st| &self.source[st..s]);
+                return Ok((text, Some(t)))
+            }
+
+            match t {
+                Token::OpenParen | Token::OpenBracket => depth += 1,
+                Token::CloseParen | Token::CloseBracket => depth -= 1,
+                _ => ()
+            }
+
+            start.get_or_insert(s);
+        }
+
+        Ok((start.map_or("", |st| &self.source[st..]), None))
+    }
+
+    /// Return the text being lexed
+    pub fn source(&self) -> &'input str {
+        self.source
+    }
+
     pub fn is_finished(&self) -> bool {
         let mut peekable = self.iterator.clone().peekable();
         peekable.peek().is_none()
@@ -164,6 +218,13 @@ impl<'input> Lexer<'input> {
 
 
 
+    /// Return true if `buf` followed by `c` is the start of a symbol
+    fn extends_symbol(buf: &str, c: char) -> bool {
+        let extended = format!("{buf}{c}");
+        SYMBOLS.iter().any(|s| s.starts_with(&extended))
+    }
+
+
     fn token_from_str(buf: &str, buf_state: &BufState) -> Option<Token> {
         let res = match buf_state {
             BufState::Idle => unreachable!(),
@@ -193,6 +254,13 @@ impl<'input> Lexer<'input> {
                     "," => Token::Comma,
                     "." => Token::Dot,
                     "::" => Token::DoubleColon,
+                    ":=" => Token::ColonEqual,
+                    "?" => Token::Question,
+                    "[" => Token::OpenBracket,
+                    "]" => Token::CloseBracket,
+                    ";" => Token::Semicolon,
+                    "|" => Token::Bar,
+                    ":" => Token::Colon,
                     _ => return None
                 }
             }
@@ -230,7 +298,8 @@ impl<'input> Iterator for Lexer<'input> {
                 continue 'char_iter;
             }
 
-            if SYMBOLS.contains(&self.buf.as_str()) {
+            // A symbol is consumed unless it is the prefix of a longer one, like ':' and '::'
+            if SYMBOLS.contains(&self.buf.as_str()) && !Self::extends_symbol(&self.buf, c) {
                 let res = self.consume_buf();
                 self.buf_state = BufState::Idle;
                 to_be_yield = Some(res);
@@ -0,0 +1,145 @@
+//! Bounded proof search over the rules of sculpt.
+//!
+//! The search applies backward the rules offered by [RuleType::is_applicable], with an iterative
+//! deepening of the height of the derivation, where the steps of 'intro' are not counted.
+//! The formulas and terms given to the rules are only taken from the sequent, outside of the
+//! quantifiers, so that each goal has finitely many candidates.
+
+use crate::engine::RuleCommand;
+use crate::logic::{Formula, Sequent, Term};
+use crate::logic::rule::RuleType;
+use crate::logic::unification::alpha_eq;
+
+
+/// Height of the derivations searched by 'auto' when none is given
+pub const DEFAULT_DEPTH: usize = 5;
+
+
+/// Search a derivation of the sequent at most `depth` rules high, not counting 'intro'. `defined` tells
+/// whether a name is defined in the context, in which case it cannot be used as an eigenvariable.
+/// Return the rules of the derivation, in the order they must be applied to the goal.
+pub fn search(sequent: &Sequent, depth: usize, defined: &dyn Fn(&str) -> bool) -> Option<Vec<RuleCommand>> {
+    (1..=depth).find_map(|d| prove(sequent, d, defined))
+}
+
+
+fn prove(sequent: &Sequent, depth: usize, defined: &dyn Fn(&str) -> bool) -> Option<Vec<RuleCommand>> {
+    if depth == 0 {
+        return None
+    }
+
+    candidates(sequent, defined).into_iter().find_map(|command| {
+        let goals = command.to_rule().apply(sequent).ok()?;
+
+        // 'intro' always makes the goal smaller, so it is not counted in the height
+        let height = match command {
+            RuleCommand::Intro => depth,
+            _ => depth - 1
+        };
+        let proofs = goals.iter()
+            .map(|g| prove(g, height, defined))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some([vec![command]].into_iter().chain(proofs).flatten().collect())
+    })
+}
+
+
+/// Return the rule commands to try on the sequent.
+/// Structural rules, 'have' and 'rename_as' never help the search and are not tried.
+fn candidates(sequent: &Sequent, defined: &dyn Fn(&str) -> bool) -> Vec<RuleCommand> {
+    let goal = sequent.consequent.as_ref();
+    let formulas = subformulas(sequent);
+    let mut res = vec![];
+
+    for rule in sequent.get_applicable_rules() {
+        match rule {
+            RuleType::Axiom => return vec![RuleCommand::Axiom],
+            RuleType::Intro => match goal {
+                Formula::Forall(v, _) if defined(v) => (),
+                _ => res.push(RuleCommand::Intro)
+            },
+            RuleType::SplitAnd => res.push(RuleCommand::Split),
+            RuleType::Keep => res.extend([RuleCommand::KeepLeft, RuleCommand::KeepRight]),
+            RuleType::FixAs => res.extend(subterms(sequent).into_iter().map(|t| RuleCommand::FixAs(Box::new(t)))),
+            RuleType::Generalize => res.extend(
+                subterms(sequent).into_iter()
+                    .filter(|t| goal.exists(t))
+                    .map(|t| RuleCommand::Generalize(Box::new(t)))
+            ),
+
+            RuleType::Trans => for f in &formulas {
+                if let Formula::Implies(a, c) = f {
+                    if alpha_eq(c, goal) { res.push(RuleCommand::Trans(a.clone())) }
+                }
+            },
+            RuleType::And => for f in &formulas {
+                if let Formula::And(a, b) = f {
+                    if alpha_eq(b, goal) { res.push(RuleCommand::AndLeft(a.clone())) }
+                    if alpha_eq(a, goal) { res.push(RuleCommand::AndRight(b.clone())) }
+                }
+            },
+            RuleType::FromOr => for f in &formulas {
+                if let Formula::Or(..) = f { res.push(RuleCommand::FromOr(Box::new(f.clone()))) }
+            },
+            RuleType::Consider => for f in &formulas {
+                if let Formula::Exists(v, _) = f {
+                    if !defined(v) { res.push(RuleCommand::Consider(Box::new(f.clone()))) }
+                }
+            },
+            RuleType::ExFalso => for f in &formulas {
+                if let Formula::Not(_) = f { res.push(RuleCommand::ExFalso(Box::new(f.clone()))) }
+            },
+            RuleType::FromBottom if !matches!(goal, Formula::Falsum) => res.push(RuleCommand::FromBottom),
+            _ => ()
+        }
+    }
+
+    res
+}
+
+
+/// Return the subformulas of the sequent which are not under a quantifier, without duplicates
+fn subformulas(sequent: &Sequent) -> Vec<Formula> {
+    fn add(f: &Formula, res: &mut Vec<Formula>) {
+        if res.iter().any(|g| alpha_eq(f, g)) {
+            return
+        }
+        res.push(f.clone());
+
+        match f {
+            Formula::Not(g) => add(g, res),
+            Formula::And(g, h) | Formula::Or(g, h) | Formula::Implies(g, h) => {
+                add(g, res);
+                add(h, res);
+            }
+            _ => ()
+        }
+    }
+
+    let mut res = vec![];
+    for h in &sequent.antecedents {
+        add(&h.formula, &mut res);
+    }
+    add(&sequent.consequent, &mut res);
+    res
+}
+
+
+/// Return the terms (and their subterms) used in the sequent outside of the quantifiers, without duplicates
+fn subterms(sequent: &Sequent) -> Vec<Term> {
+    fn add(t: &Term, res: &mut Vec<Term>) {
+        if !res.contains(t) {
+            res.push(t.clone());
+        }
+        t.1.iter().for_each(|u| add(u, res));
+    }
+
+    let mut res = vec![];
+    for f in subformulas(sequent) {
+        if let Formula::Relation(_, terms) = f {
+            terms.iter().for_each(|t| add(t, &mut res));
+        }
+    }
+    res
+}
@@ -0,0 +1,269 @@
+//! Decision procedure for intuitionistic propositional logic, using the contraction-free
+//! sequent calculus G4ip (also called LJT) of Dyckhoff.
+//!
+//! Relations and quantified formulas are considered as atoms, and `~F` as `F => falsum`.
+//! Each rule of G4ip is translated into primitive rules of sculpt: the antecedents removed by
+//! a left rule are only forgotten by the search, they stay in the sequents of the proof.
+
+use crate::logic::Formula;
+use crate::logic::rule::{Rule, Side};
+use crate::logic::unification::alpha_eq;
+
+
+/// Search a proof of `antecedents ⊢ goal`. Return the primitive rules proving it, in the
+/// order they must be applied to the goal, or None if the sequent is not provable.
+pub fn prove(antecedents: &[Formula], goal: &Formula) -> Option<Vec<Rule>> {
+    // Axioms
+    if antecedents.iter().any(|f| matches!(f, Formula::Falsum)) {
+        return Some(from_falsum(goal))
+    }
+    if antecedents.iter().any(|f| alpha_eq(f, goal)) {
+        return Some(vec![Rule::Axiom])
+    }
+
+    // Invertible right rules
+    match goal {
+        Formula::And(a, b) => {
+            let mut res = vec![Rule::SplitAnd];
+            res.extend(prove(antecedents, a)?);
+            res.extend(prove(antecedents, b)?);
+            return Some(res)
+        }
+        Formula::Implies(a, b) => return prove(&with(antecedents, [a.as_ref()]), b).map(|p| [vec![Rule::Intro], p].concat()),
+        Formula::Not(a) => return prove(&with(antecedents, [a.as_ref()]), &Formula::Falsum).map(|p| [vec![Rule::FromBottom], p].concat()),
+        _ => ()
+    }
+
+    // Invertible left rules
+    for (i, h) in antecedents.iter().enumerate() {
+        let others = without(antecedents, i);
+
+        match h {
+            Formula::And(a, b) => {
+                let rest = prove(&with(&others, [a.as_ref(), b.as_ref()]), goal)?;
+                let rest = cut(b, vec![Rule::And(Side::Left, a.clone()), Rule::Axiom], rest);
+                return Some(cut(a, vec![Rule::And(Side::Right, b.clone()), Rule::Axiom], rest))
+            }
+            Formula::Or(a, b) => {
+                let mut res = vec![Rule::FromOr(Box::new(h.clone())), Rule::Axiom];
+                res.extend(prove(&with(&others, [a.as_ref()]), goal)?);
+                res.extend(prove(&with(&others, [b.as_ref()]), goal)?);
+                return Some(res)
+            }
+            _ => ()
+        }
+
+        let (x, d) = match implication(h) {
+            Some(i) => i,
+            None => continue
+        };
+
+        match x {
+            // falsum => D is useless
+            Formula::Falsum => return prove(&others, goal),
+
+            x if antecedents.iter().any(|f| alpha_eq(f, x)) => {
+                let rest = prove(&with(&others, [&d]), goal)?;
+                return Some(cut(&d, modus_ponens(h, vec![Rule::Axiom]), rest))
+            }
+
+            // A /\ B => D becomes A => B => D
+            Formula::And(a, b) => {
+                let curried = Formula::Implies(a.clone(), Box::new(Formula::Implies(b.clone(), Box::new(d))));
+                let rest = prove(&with(&others, [&curried]), goal)?;
+
+                let proof = [
+                    vec![Rule::Intro, Rule::Intro],
+                    modus_ponens(h, vec![Rule::SplitAnd, Rule::Axiom, Rule::Axiom])
+                ].concat();
+                return Some(cut(&curried, proof, rest))
+            }
+
+            // A \/ B => D becomes A => D and B => D
+            Formula::Or(a, b) => {
+                let left = Formula::Implies(a.clone(), Box::new(d.clone()));
+                let right = Formula::Implies(b.clone(), Box::new(d));
+                let rest = prove(&with(&others, [&left, &right]), goal)?;
+
+                let left_proof = [vec![Rule::Intro], modus_ponens(h, vec![Rule::Keep(Side::Left), Rule::Axiom])].concat();
+                let right_proof = [vec![Rule::Intro], modus_ponens(h, vec![Rule::Keep(Side::Right), Rule::Axiom])].concat();
+                return Some(cut(&left, left_proof, cut(&right, right_proof, rest)))
+            }
+
+            _ => ()
+        }
+    }
+
+    // Non-invertible rules, each possibility is tried
+    if let Formula::Or(a, b) = goal {
+        if let Some(p) = prove(antecedents, a) {
+            return Some([vec![Rule::Keep(Side::Left)], p].concat())
+        }
+        if let Some(p) = prove(antecedents, b) {
+            return Some([vec![Rule::Keep(Side::Right)], p].concat())
+        }
+    }
+
+    // (A => B) => D: prove A => B with B => D, then the goal with D
+    for (i, h) in antecedents.iter().enumerate() {
+        let (x, d) = match implication(h) {
+            Some(i) => i,
+            None => continue
+        };
+        let (_, b) = match implication(x) {
+            Some(i) => i,
+            None => continue
+        };
+
+        let others = without(antecedents, i);
+        let weakened = Formula::Implies(Box::new(b), Box::new(d.clone()));
+
+        let x_proof = match prove(&with(&others, [&weakened]), x) {
+            Some(p) => p,
+            None => continue
+        };
+        let rest = match prove(&with(&others, [&d]), goal) {
+            Some(p) => p,
+            None => continue
+        };
+
+        let weakened_proof = [vec![Rule::Intro], modus_ponens(h, vec![introduction(x), Rule::Axiom])].concat();
+        return Some(cut(&weakened, weakened_proof, cut(&d, modus_ponens(h, x_proof), rest)))
+    }
+
+    None
+}
+
+
+
+/// Search a classical proof of `antecedents ⊢ goal`: `from_bottom`, then an intuitionistic proof
+/// of falsum, which exists iff the sequent is a classical tautology (Glivenko's theorem).
+pub fn prove_classical(antecedents: &[Formula], goal: &Formula) -> Option<Vec<Rule>> {
+    // The antecedent added by 'from_bottom'
+    let negated = match goal {
+        Formula::Not(f) => f.as_ref().clone(),
+        f => Formula::Not(Box::new(f.clone()))
+    };
+
+    let proof = prove(&with(antecedents, [&negated]), &Formula::Falsum)?;
+    Some([vec![Rule::FromBottom], proof].concat())
+}
+
+/// Return `A` and `B` if the formula is `A => B`, or `A` and falsum if it is `~A`
+fn implication(f: &Formula) -> Option<(&Formula, Formula)> {
+    match f {
+        Formula::Implies(a, b) => Some((a.as_ref(), *b.clone())),
+        Formula::Not(a) => Some((a.as_ref(), Formula::Falsum)),
+        _ => None
+    }
+}
+
+/// Rule introducing the implication or negation `f` in the goal
+fn introduction(f: &Formula) -> Rule {
+    match f {
+        Formula::Not(_) => Rule::FromBottom,
+        _ => Rule::Intro
+    }
+}
+
+
+/// Prove the conclusion `B` of the antecedent `h`, stated as `A => B` or `~A`, given a proof of `A`
+fn modus_ponens(h: &Formula, proof: Vec<Rule>) -> Vec<Rule> {
+    match h {
+        Formula::Not(_) => [vec![Rule::ExFalso(Box::new(h.clone()))], proof, vec![Rule::Axiom]].concat(),
+        Formula::Implies(a, _) => [vec![Rule::Trans(a.clone()), Rule::Axiom], proof].concat(),
+        _ => unreachable!()
+    }
+}
+
+
+/// Prove the goal with falsum in the antecedents
+fn from_falsum(goal: &Formula) -> Vec<Rule> {
+    match goal {
+        Formula::Falsum => vec![Rule::Axiom],
+        _ => vec![Rule::FromBottom, Rule::Axiom]
+    }
+}
+
+
+/// Add `f` to the antecedents with its proof, then prove the goal with `rest`
+fn cut(f: &Formula, proof: Vec<Rule>, rest: Vec<Rule>) -> Vec<Rule> {
+    [vec![Rule::Cut(None, Box::new(f.clone()))], proof, rest].concat()
+}
+
+
+
+fn with<'a>(antecedents: &[Formula], formulas: impl IntoIterator<Item = &'a Formula>) -> Vec<Formula> {
+    let mut res = antecedents.to_vec();
+    res.extend(formulas.into_iter().cloned());
+    res
+}
+
+fn without(antecedents: &[Formula], i: usize) -> Vec<Formula> {
+    let mut res = antecedents.to_vec();
+    res.remove(i);
+    res
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::error::Error;
+    use crate::kernel;
+    use crate::proof::Proof;
+    use crate::syntax::lexer::Lexer;
+    use crate::tactics::tauto;
+
+    /// Prove the formula with `tauto`, and check the derivation with the kernel
+    fn tauto_str(goal: &str, classical: bool) -> Result<(), Error> {
+        let goal = Formula::parse(&mut Lexer::from(goal)).expect("valid formula");
+        let mut proof = Proof::start(Box::new(goal));
+        tauto(&mut proof, classical)?;
+        assert!(proof.is_finished());
+        kernel::check(&proof, &HashMap::new())
+    }
+
+    #[test]
+    fn intuitionistic_tautologies() {
+        let tautologies = [
+            "P => P",
+            "falsum => P",
+            "(P => Q) => (Q => R) => P => R",
+            "P /\\ Q => Q /\\ P",
+            "P \\/ Q => Q \\/ P",
+            "((P \\/ Q) => R) => (P => R) /\\ (Q => R)",
+            "~(P \\/ Q) => ~P /\\ ~Q",
+            "~~(P \\/ ~P)",
+            "~~~P => ~P",
+            "((((P => Q) => P) => P) => Q) => Q",
+            "(forall x, P(x)) /\\ Q(a) => Q(a) /\\ (forall x, P(x))"
+        ];
+        for f in tautologies {
+            assert!(tauto_str(f, false).is_ok(), "{f}");
+            assert!(tauto_str(f, true).is_ok(), "{f}");
+        }
+    }
+
+    #[test]
+    fn classical_tautologies() {
+        for f in ["P \\/ ~P", "((P => Q) => P) => P", "~~P => P", "(~Q => ~P) => P => Q"] {
+            let msg = tauto_str(f, false).expect_err(f).to_string();
+            assert_eq!(msg, "The goal is not provable in intuitionistic propositional logic, but it is classically: use 'tauto classical'");
+            assert!(tauto_str(f, true).is_ok(), "{f}");
+        }
+    }
+
+    #[test]
+    fn invalid_formulas() {
+        for f in ["(((P => Q) => R) => S) => ((Q => R) => S)", "P => Q", "(P => Q) => (Q => P)", "~(P /\\ Q) => ~P"] {
+            let expected = "The goal is not provable in propositional logic";
+            assert_eq!(tauto_str(f, false).expect_err(f).to_string(), expected);
+            assert!(prove_classical(&[], &Formula::parse(&mut Lexer::from(f)).unwrap()).is_none(), "{f}");
+        }
+        let msg = tauto_str("(forall x, P(x)) => P(a)", true).expect_err("quantifiers are atoms").to_string();
+        assert_eq!(msg, "The goal is not provable in classical propositional logic, considering quantified formulas as atoms");
+    }
+}
@@ -0,0 +1,365 @@
+//! Tactics: commands building several steps of a derivation at once.
+//!
+//! A tactic only applies primitive rules to the proof, so that its result is checked by the
+//! kernel and exported like any other derivation. If a tactic fails, the proof is left untouched.
+//! The proof search of [auto] returns rule commands instead, which are recorded as such.
+
+pub mod auto;
+mod g4ip;
+pub mod tableau;
+
+use std::collections::HashMap;
+use crate::error::Error;
+use crate::logic::{is_metavariable, Formula, Sequent, Term};
+use crate::logic::rule::Rule;
+use crate::logic::unification::{alpha_eq, unify};
+use crate::proof::Proof;
+
+
+/// Prove the current goal using the theorem `name`, stated as `forall xs, H1 => ... => Hn => C`:
+/// `C` is unified with the goal, which is replaced by the instantiated `H1`, ..., `Hn`.
+/// The conclusion is first searched with all the premises, then with fewer.
+///
+/// The derivation uses `trans` for each premise, then `Use` and `axiom` on the instantiated theorem.
+pub fn apply(proof: &mut Proof, theorems: &HashMap<String, Box<Formula>>, name: &str) -> Result<(), Error> {
+    let thm = theorems.get(name).ok_or(Error::CommandError(format!("Unknown theorem {name}")))?;
+    use_theorem(proof, name, thm, true)
+        .map_err(|e| e.unwrap_or(Error::CommandError(format!("The conclusion of {name} does not match the goal"))))
+}
+
+
+/// Prove the current goal using the theorem `name`, whose statement (once its leading universal
+/// quantifiers and schematic variables are instantiated) must unify with the goal.
+pub fn exact(proof: &mut Proof, theorems: &HashMap<String, Box<Formula>>, name: &str) -> Result<(), Error> {
+    let thm = theorems.get(name).ok_or(Error::CommandError(format!("Unknown theorem {name}")))?;
+    use_theorem(proof, name, thm, false)
+        .map_err(|e| e.unwrap_or(Error::CommandError(format!("The statement of {name} does not match the goal"))))
+}
+
+
+/// Prove the current goal with one of its antecedents, up to the renaming of bound variables,
+/// or else with the first of the given theorems matching it like with `exact`.
+pub fn assumption(proof: &mut Proof, theorems: &[(&str, &Formula)]) -> Result<(), Error> {
+    if proof.current_goal().is_none() {
+        return Err(Error::CommandError("Proof is finished".to_string()))
+    }
+
+    let mut res = proof.clone();
+    if res.apply(Rule::Axiom).is_ok() {
+        *proof = res;
+        return Ok(())
+    }
+
+    for (name, thm) in theorems {
+        if use_theorem(proof, name, thm, false).is_ok() {
+            return Ok(())
+        }
+    }
+
+    Err(Error::CommandError("No antecedent nor theorem matches the goal".to_string()))
+}
+
+
+/// Prove the current goal from contradictory antecedents: `falsum`, or both `F` and `~F`.
+///
+/// The derivation uses `from_bottom` (if the goal is not `falsum`), then `axiom` on `falsum`,
+/// or `exfalso ~F` and `axiom` on both `F` and `~F`.
+pub fn contradiction(proof: &mut Proof) -> Result<(), Error> {
+    let goal = proof.current_goal().ok_or(Error::CommandError("Proof is finished".to_string()))?;
+    let antecedents = goal.antecedents.iter().map(|h| &h.formula).collect::<Vec<_>>();
+
+    let has_falsum = antecedents.iter().any(|f| matches!(f, Formula::Falsum));
+    let pair = antecedents.iter()
+        .filter_map(|f| match f {
+            Formula::Not(g) => Some(g.as_ref()),
+            _ => None
+        })
+        .find(|g| antecedents.iter().any(|f| alpha_eq(f, g)))
+        .cloned();
+
+    let mut rules = vec![];
+    if !matches!(goal.consequent.as_ref(), Formula::Falsum) {
+        rules.push(Rule::FromBottom);
+    }
+    match (has_falsum, pair) {
+        (true, _) => rules.push(Rule::Axiom),
+        (false, Some(f)) => rules.extend([
+            Rule::ExFalso(Box::new(Formula::Not(Box::new(f)))),
+            Rule::Axiom,
+            Rule::Axiom
+        ]),
+        (false, None) => return Err(Error::CommandError("No contradiction found in the antecedents".to_string()))
+    }
+
+    let mut res = proof.clone();
+    for rule in rules {
+        res.apply(rule)?;
+    }
+
+    *proof = res;
+    Ok(())
+}
+
+
+/// Prove the current goal in propositional logic, considering relations and quantified formulas as atoms.
+/// Return an Err saying whether the goal is provable at all if it is not provable in the chosen logic.
+///
+/// The intuitionistic proof is searched with G4ip (see [g4ip]), which also gives the classical one.
+pub fn tauto(proof: &mut Proof, classical: bool) -> Result<(), Error> {
+    let goal = proof.current_goal().ok_or(Error::CommandError("Proof is finished".to_string()))?;
+
+    let antecedents = goal.antecedents.iter().map(|h| h.formula.clone()).collect::<Vec<_>>();
+    let intuitionistic = || g4ip::prove(&antecedents, &goal.consequent);
+    let classical_proof = || g4ip::prove_classical(&antecedents, &goal.consequent);
+
+    let atoms = match has_quantifiers(goal) {
+        true => ", considering quantified formulas as atoms",
+        false => ""
+    };
+    let rules = match (intuitionistic(), classical) {
+        (Some(p), _) => p,
+        (None, true) => classical_proof()
+            .ok_or(Error::CommandError(format!("The goal is not provable in classical propositional logic{atoms}")))?,
+        (None, false) => return Err(Error::CommandError(match classical_proof() {
+            Some(_) => format!("The goal is not provable in intuitionistic propositional logic{atoms}, but it is classically: use 'tauto classical'"),
+            None => format!("The goal is not provable in propositional logic{atoms}")
+        }))
+    };
+
+    let mut res = proof.clone();
+    for rule in rules {
+        res.apply(rule)?;
+    }
+
+    *proof = res;
+    Ok(())
+}
+
+
+/// Prove the current goal in first-order logic, by refuting its antecedents and its negation with
+/// a tableau (see [tableau]) using at most `bound` instances of each universal formula on a branch.
+/// `defined` tells whether a name is defined in the context, so that it is not used as an eigenvariable.
+pub fn firstorder(proof: &mut Proof, bound: usize, defined: &dyn Fn(&str) -> bool) -> Result<(), Error> {
+    let goal = proof.current_goal().ok_or(Error::CommandError("Proof is finished".to_string()))?;
+
+    let (mut rules, refuted) = match goal.consequent.as_ref() {
+        Formula::Falsum => (vec![], goal.clone()),
+        _ => (vec![Rule::FromBottom], Rule::FromBottom.apply(goal)?.remove(0))
+    };
+
+    let formulas = refuted.antecedents.iter().map(|h| h.formula.clone()).collect::<Vec<_>>();
+    let mut names = formulas.iter().flat_map(|f| f.variables()).collect::<Vec<_>>();
+    names.extend(refuted.domain());
+
+    let refutation = tableau::refute(&formulas, bound, &|n| defined(n) || names.iter().any(|m| m == n))?
+        .ok_or(Error::CommandError(format!("No proof found with at most {bound} instances of each universal formula on a branch")))?;
+    rules.extend(refutation);
+
+    let mut res = proof.clone();
+    for rule in rules {
+        res.apply(rule)?;
+    }
+
+    *proof = res;
+    Ok(())
+}
+
+
+/// Return the theorems whose conclusion, or one of its sub-conclusions `Hk => ... => C`,
+/// matches the pattern. The holes of the pattern are the terms and formulas `_` (each one
+/// standing for anything), and the variables `?x` and `?A` (standing for the same thing
+/// at each occurrence).
+pub fn search<'a>(theorems: &[(&'a str, &'a Formula)], pattern: &Formula) -> Vec<(&'a str, &'a Formula)> {
+    let mut holes = vec![];
+    let pattern = name_holes(pattern, &mut holes);
+    for m in pattern.metavariables() {
+        if !holes.contains(&m) {
+            holes.push(m);
+        }
+    }
+
+    theorems.iter()
+        .filter(|(_, thm)| {
+            let (_, fresh, body) = open(thm, pattern.variables(), usize::MAX);
+            let mut variables = holes.clone();
+            variables.extend(fresh);
+
+            (0..=premises(&body).len())
+                .any(|n| unify(&pattern, conclusion(&body, n), &variables, &Default::default()).is_some())
+        })
+        .cloned()
+        .collect()
+}
+
+
+
+/// Prove the current goal with the theorem, using as many premises as possible if `with_premises`,
+/// else none of them.
+/// Return Err(None) if the theorem does not match the goal.
+fn use_theorem(proof: &mut Proof, name: &str, thm: &Formula, with_premises: bool) -> Result<(), Option<Error>> {
+    let goal = proof.current_goal().ok_or(Error::CommandError("Proof is finished".to_string()))?;
+
+    let mut used = goal.consequent.variables();
+    used.extend(goal.antecedents.iter().flat_map(|h| h.formula.variables()));
+
+    let mut quantifiers = 0;
+    let mut f = thm;
+    while let Formula::Forall(_, g) = f {
+        quantifiers += 1;
+        f = g;
+    }
+
+    // Leading universal quantifiers, renamed apart from the variables of the goal: all of them,
+    // then fewer, so that a goal which is itself universal can be matched.
+    // The conclusion is unified with as many premises as possible, and the metavariables
+    // of the proof can be solved too
+    let (variables, fresh, n, subst) = (0..=quantifiers).rev()
+        .find_map(|k| {
+            let (variables, fresh, body) = open(thm, used.clone(), k);
+
+            let mut variables_of_unification = fresh.clone();
+            variables_of_unification.extend(thm.metavariables());
+            variables_of_unification.extend(proof.metavariables());
+
+            let max = if with_premises { premises(&body).len() } else { 0 };
+            (0..=max).rev()
+                .find_map(|n| unify(conclusion(&body, n), &goal.consequent, &variables_of_unification, &Default::default()).map(|s| (n, s)))
+                .map(|(n, s)| (variables, fresh, n, s))
+        })
+        .ok_or(None)?;
+
+    let mut terms = vec![];
+    for (v, f) in variables.iter().zip(&fresh) {
+        match subst.term(f) {
+            Some(t) if !t.domain().iter().any(|d| fresh.contains(d)) => terms.push(t),
+            _ => return Err(Some(Error::CommandError(format!("Cannot infer {v} from the goal, use 'Use {name} with ...' instead"))))
+        }
+    }
+
+    // The schematic variables which are not instantiated must be the ones of the current theorem
+    let mut schema = vec![];
+    for m in thm.metavariables() {
+        match subst.formula(&m) {
+            Some(f) => schema.push((m, f.clone())),
+            None if proof.goal.metavariables().contains(&m) => (),
+            None => return Err(Some(Error::CommandError(format!("Cannot infer {m} from the goal, use 'Use {name} [...]' instead"))))
+        }
+    }
+
+    let instance = thm.substitute_relations(&schema).instantiate(&terms)?;
+    let hypotheses = premises(&instance).into_iter().take(n).cloned().collect::<Vec<_>>();
+
+    let mut res = proof.clone();
+    res.instantiate(&subst)?;
+    for h in hypotheses.iter().rev() {
+        res.apply(Rule::Trans(Box::new(h.clone())))?;
+    }
+    res.apply(Rule::Use(name.to_string(), Box::new(thm.clone()), schema, terms))?;
+    res.apply(Rule::Axiom)?;
+
+    *proof = res;
+    Ok(())
+}
+
+
+/// Return whether a quantifier occurs in the sequent
+fn has_quantifiers(sequent: &Sequent) -> bool {
+    fn quantified(f: &Formula) -> bool {
+        match f {
+            Formula::Forall(..) | Formula::Exists(..) => true,
+            Formula::Not(g) => quantified(g),
+            Formula::And(g, h) | Formula::Or(g, h) | Formula::Implies(g, h) => quantified(g) || quantified(h),
+            Formula::Falsum | Formula::Relation(..) => false
+        }
+    }
+
+    quantified(&sequent.consequent) || sequent.antecedents.iter().any(|h| quantified(&h.formula))
+}
+
+
+/// Remove at most `max` leading universal quantifiers of the theorem, renaming their variables apart
+/// from `used`. Return the variables, their new names and the body of the theorem.
+fn open(thm: &Formula, mut used: Vec<String>, max: usize) -> (Vec<String>, Vec<String>, Formula) {
+    used.extend(thm.variables());
+
+    let mut variables = vec![];
+    let mut fresh = vec![];
+    let mut body = thm.clone();
+    while let Formula::Forall(x, f) = &body {
+        if variables.len() == max {
+            break
+        }
+
+        let renamed = (1..).map(|p| format!("{x}{}", "'".repeat(p)))
+            .find(|n| !used.contains(n))
+            .expect("Infinite iterator");
+        used.push(renamed.clone());
+
+        variables.push(x.clone());
+        body = f.substitute(x, &Term(renamed.clone(), vec![]));
+        fresh.push(renamed);
+    }
+
+    (variables, fresh, body)
+}
+
+
+/// Replace each hole `_` of the pattern by a distinct variable `?_n`, and store them in `holes`
+/// with the `?x` term holes
+fn name_holes(pattern: &Formula, holes: &mut Vec<String>) -> Formula {
+    let mut sub = |f: &Formula| Box::new(name_holes(f, holes));
+    match pattern {
+        Formula::Relation(r, terms) if r == "_" && terms.is_empty() => {
+            let hole = format!("?_{}", holes.len() + 1);
+            holes.push(hole.clone());
+            Formula::Relation(hole, vec![])
+        }
+        Formula::Falsum => Formula::Falsum,
+        Formula::Relation(r, terms) => Formula::Relation(r.clone(), terms.iter().map(|t| name_term_holes(t, holes)).collect()),
+        Formula::Not(f) => Formula::Not(sub(f)),
+        Formula::Or(f1, f2) => Formula::Or(sub(f1), sub(f2)),
+        Formula::And(f1, f2) => Formula::And(sub(f1), sub(f2)),
+        Formula::Implies(f1, f2) => Formula::Implies(sub(f1), sub(f2)),
+        Formula::Forall(x, f) => Formula::Forall(x.clone(), sub(f)),
+        Formula::Exists(x, f) => Formula::Exists(x.clone(), sub(f)),
+    }
+}
+
+fn name_term_holes(t: &Term, holes: &mut Vec<String>) -> Term {
+    match t {
+        Term(v, args) if v == "_" && args.is_empty() => {
+            let hole = format!("?_{}", holes.len() + 1);
+            holes.push(hole.clone());
+            Term(hole, vec![])
+        }
+        Term(v, args) if is_metavariable(v) && args.is_empty() => {
+            if !holes.contains(v) {
+                holes.push(v.clone());
+            }
+            t.clone()
+        }
+        Term(f, args) => Term(f.clone(), args.iter().map(|u| name_term_holes(u, holes)).collect())
+    }
+}
+
+
+
+/// Return the premises of the formula `H1 => ... => Hn => C`
+fn premises(f: &Formula) -> Vec<&Formula> {
+    match f {
+        Formula::Implies(h, c) => {
+            let mut res = vec![h.as_ref()];
+            res.extend(premises(c));
+            res
+        }
+        _ => vec![]
+    }
+}
+
+/// Return the formula `C` of `H1 => ... => Hn => C`, after the n first premises
+fn conclusion(f: &Formula, n: usize) -> &Formula {
+    match (f, n) {
+        (Formula::Implies(_, c), n) if n > 0 => conclusion(c, n - 1),
+        _ => f
+    }
+}
@@ -0,0 +1,553 @@
+//! Free-variable tableau prover for first-order logic.
+//!
+//! The tableau refutes a set of formulas: the universal formulas are instantiated with fresh
+//! free variables, and a branch is closed by unifying a literal with the negation of another.
+//! The existential formulas are instantiated with Skolem terms over the free variables of their branch.
+//! The number of instantiations of each universal formula on a branch is bounded, and increased up to a maximum.
+//!
+//! A closed tableau is translated into primitive rules proving falsum: each Skolem term becomes
+//! an eigenvariable introduced by `consider`, and the propositional steps are proven with G4ip.
+
+use crate::error::Error;
+use crate::logic::{Formula, Term};
+use crate::logic::rule::Rule;
+use crate::logic::unification::{unify, Substitution};
+use super::g4ip;
+
+
+/// Maximum number of instantiations of each universal formula on a branch used by 'firstorder' when none is given
+pub const DEFAULT_BOUND: usize = 3;
+
+/// Maximum number of expansions of the search
+const MAX_STEPS: usize = 200_000;
+
+// Names of the free variables and Skolem functions, which cannot be written in a formula
+const VARIABLE_PREFIX: &str = "#X";
+const SKOLEM_PREFIX: &str = "#sk";
+
+
+/// Closed tableau, each node being the expansion of a formula of the branch.
+#[derive(Clone, Debug)]
+enum Tableau {
+    /// The branch contains falsum, or a literal and its negation (after substitution)
+    Close(Formula, Formula),
+    /// The formula is replaced by its components
+    Alpha(Formula, Vec<Formula>, Box<Tableau>),
+    /// The branch is split into one branch for each component of the formula
+    Beta(Formula, Formula, Formula, Box<Tableau>, Box<Tableau>),
+    /// Instance of a universal formula, with the bound variable and its instance
+    Gamma(Formula, Term, Formula, Box<Tableau>),
+    /// Instance of an existential formula, with the Skolem function used
+    Delta(Formula, String, Box<Tableau>)
+}
+
+
+/// Formulas of a branch
+#[derive(Clone, Debug, Default)]
+struct Branch {
+    todo: Vec<Formula>,
+    literals: Vec<Formula>,
+    universals: Vec<(Formula, usize)>,  // With the number of instances left, expanded again each time the branch needs a new instance
+    variables: Vec<String>,             // Free variables introduced on this branch
+    instances: usize                    // Instances allowed for each universal formula on this branch
+}
+
+
+/// Result of a search: the substitution closing every branch, and the tableaux of the
+/// current branch and of the pending ones, in order
+type Found = Option<(Substitution, Vec<Tableau>)>;
+
+/// Proof of the pending branches, given the substitution closing the previous ones
+type Continuation<'a> = &'a mut dyn FnMut(&mut Search, Substitution) -> Found;
+
+
+#[derive(Default)]
+struct Search {
+    variables: Vec<String>,
+    skolems: Vec<String>,       // Bound variable each Skolem function replaces
+    steps: usize,
+    max_steps: usize
+}
+
+
+/// Search a refutation of the formulas, with at most `bound` instantiations of each universal formula on a branch.
+/// `used` are the names which cannot be used for the eigenvariables.
+/// Return the primitive rules proving falsum from the formulas, or None if no refutation was found.
+pub fn refute(formulas: &[Formula], bound: usize, used: &dyn Fn(&str) -> bool) -> Result<Option<Vec<Rule>>, Error> {
+    refute_within(formulas, bound, used, MAX_STEPS)
+}
+
+/// Search a refutation like [refute], giving up after `max_steps` expansions.
+fn refute_within(formulas: &[Formula], bound: usize, used: &dyn Fn(&str) -> bool, max_steps: usize) -> Result<Option<Vec<Rule>>, Error> {
+    let mut search = Search { max_steps, ..Default::default() };
+
+    for instances in 0..=bound {
+        let branch = Branch { todo: formulas.to_vec(), instances, ..Default::default() };
+        let found = search.expand(branch, &Substitution::default(), &mut |_, s| Some((s, vec![])));
+
+        if search.steps > max_steps {
+            return Err(Error::CommandError(format!("No refutation found in {max_steps} steps")))
+        }
+        if let Some((subst, tableaux)) = found {
+            let mut grounding = Grounding { subst, skolems: search.skolems.clone(), names: vec![], reserved: vec![], used };
+            return Ok(Some(grounding.rules(&tableaux[0])))
+        }
+    }
+
+    Ok(None)
+}
+
+
+
+impl Search {
+    /// Close the branch, then the pending ones with `k`
+    fn expand(&mut self, mut branch: Branch, subst: &Substitution, k: Continuation) -> Found {
+        self.steps += 1;
+        if self.steps > self.max_steps {
+            return None
+        }
+
+        // Literals, conjunctive and existential formulas first
+        let position = branch.todo.iter().position(|f| !is_beta(f))
+            .or(if branch.todo.is_empty() { None } else { Some(0) });
+
+        let f = match position {
+            Some(i) => branch.todo.remove(i),
+            None => return self.instantiate(branch, subst, k)
+        };
+
+        let wrap = |found: Found, node: &dyn Fn(Tableau) -> Tableau| found.map(|(s, mut t)| {
+            t[0] = node(t[0].clone());
+            (s, t)
+        });
+
+        match &f {
+            Formula::Falsum => {
+                let (s, rest) = k(self, subst.clone())?;
+                Some((s, [vec![Tableau::Close(Formula::Falsum, Formula::Falsum)], rest].concat()))
+            }
+            Formula::Not(g) if matches!(g.as_ref(), Formula::Falsum) => self.expand(branch, subst, k),
+            Formula::Relation(..) | Formula::Not(_) if is_literal(&f) => self.close(branch, f, subst, k),
+
+            _ => match decompose(&f) {
+                Expansion::Alpha(components) => {
+                    branch.todo.extend(components.clone());
+                    wrap(self.expand(branch, subst, k), &|t| Tableau::Alpha(f.clone(), components.clone(), Box::new(t)))
+                }
+
+                Expansion::Beta(a, b) => {
+                    let mut left = branch.clone();
+                    let mut right = branch;
+                    left.todo.push(a.clone());
+                    right.todo.push(b.clone());
+
+                    let found = self.expand(left, subst, &mut |search, s| search.expand(right.clone(), &s, k));
+                    found.map(|(s, mut t)| {
+                        let (l, r) = (t.remove(0), t.remove(0));
+                        t.insert(0, Tableau::Beta(f.clone(), a.clone(), b.clone(), Box::new(l), Box::new(r)));
+                        (s, t)
+                    })
+                }
+
+                Expansion::Gamma => {
+                    branch.universals.push((f, branch.instances));
+                    self.expand(branch, subst, k)
+                }
+
+                Expansion::Delta(x, body, negated) => {
+                    let symbol = format!("{SKOLEM_PREFIX}{}", self.skolems.len());
+                    let skolem = Term(symbol.clone(), branch.variables.iter().map(|v| Term(v.clone(), vec![])).collect());
+                    self.skolems.push(x.clone());
+
+                    let instance = match negated {
+                        true => Formula::Not(Box::new(body.substitute(&x, &skolem))),
+                        false => body.substitute(&x, &skolem)
+                    };
+                    branch.todo.push(instance);
+                    wrap(self.expand(branch, subst, k), &|t| Tableau::Delta(f.clone(), symbol.clone(), Box::new(t)))
+                }
+
+                // Vacuous quantifiers cannot be instantiated with the rules of sculpt
+                Expansion::None => self.expand(branch, subst, k)
+            }
+        }
+    }
+
+
+    /// Close the branch with the literal, or add it to the branch
+    fn close(&mut self, mut branch: Branch, literal: Formula, subst: &Substitution, k: Continuation) -> Found {
+        for other in &branch.literals {
+            let (positive, negative) = match (&literal, other) {
+                (Formula::Not(a), b) | (b, Formula::Not(a)) if !matches!(b, Formula::Not(_)) => (b, a.as_ref()),
+                _ => continue
+            };
+
+            if let Some(s) = unify(positive, negative, &self.variables, subst) {
+                if let Some((s, rest)) = k(self, s) {
+                    return Some((s, [vec![Tableau::Close(positive.clone(), Formula::Not(Box::new(negative.clone())))], rest].concat()))
+                }
+            }
+            if self.steps > self.max_steps {
+                return None
+            }
+        }
+
+        branch.literals.push(literal);
+        self.expand(branch, subst, k)
+    }
+
+
+    /// Instantiate the first universal formula of the branch which has instances left with a new free variable
+    fn instantiate(&mut self, mut branch: Branch, subst: &Substitution, k: Continuation) -> Found {
+        let (f, left) = match branch.universals.iter().position(|(_, n)| *n > 0) {
+            Some(i) => branch.universals.remove(i),
+            None => return None
+        };
+        let variable = format!("{VARIABLE_PREFIX}{}", self.variables.len());
+        self.variables.push(variable.clone());
+
+        let t = Term(variable.clone(), vec![]);
+        let instance = match &f {
+            Formula::Forall(x, body) => body.substitute(x, &t),
+            Formula::Not(g) => match g.as_ref() {
+                Formula::Exists(x, body) => Formula::Not(Box::new(body.substitute(x, &t))),
+                _ => unreachable!()
+            },
+            _ => unreachable!()
+        };
+
+        branch.universals.push((f.clone(), left - 1));
+        branch.variables.push(variable);
+        branch.todo.push(instance.clone());
+
+        self.expand(branch, subst, k).map(|(s, mut tableaux)| {
+            tableaux[0] = Tableau::Gamma(f, t, instance, Box::new(tableaux[0].clone()));
+            (s, tableaux)
+        })
+    }
+}
+
+
+
+enum Expansion {
+    Alpha(Vec<Formula>),
+    Beta(Formula, Formula),
+    Gamma,
+    Delta(String, Formula, bool),   // Bound variable, body, and whether the instance is negated
+    None
+}
+
+/// Return how the formula is expanded in the tableau
+fn decompose(f: &Formula) -> Expansion {
+    let not = |f: &Formula| Formula::Not(Box::new(f.clone()));
+
+    match f {
+        Formula::And(a, b) => Expansion::Alpha(vec![*a.clone(), *b.clone()]),
+        Formula::Or(a, b) => Expansion::Beta(*a.clone(), *b.clone()),
+        Formula::Implies(a, b) => Expansion::Beta(not(a), *b.clone()),
+        Formula::Forall(x, body) if body.domain().contains(x) => Expansion::Gamma,
+        Formula::Exists(x, body) if body.domain().contains(x) => Expansion::Delta(x.clone(), *body.clone(), false),
+
+        Formula::Not(g) => match g.as_ref() {
+            Formula::Not(a) => Expansion::Alpha(vec![*a.clone()]),
+            Formula::Or(a, b) => Expansion::Alpha(vec![not(a), not(b)]),
+            Formula::Implies(a, b) => Expansion::Alpha(vec![*a.clone(), not(b)]),
+            Formula::And(a, b) => Expansion::Beta(not(a), not(b)),
+            Formula::Exists(x, body) if body.domain().contains(x) => Expansion::Gamma,
+            Formula::Forall(x, body) if body.domain().contains(x) => Expansion::Delta(x.clone(), *body.clone(), true),
+            _ => Expansion::None
+        },
+
+        _ => Expansion::None
+    }
+}
+
+fn is_beta(f: &Formula) -> bool {
+    !is_literal(f) && matches!(decompose(f), Expansion::Beta(..))
+}
+
+fn is_literal(f: &Formula) -> bool {
+    match f {
+        Formula::Relation(..) => true,
+        Formula::Not(g) => matches!(g.as_ref(), Formula::Relation(..)),
+        _ => false
+    }
+}
+
+
+
+/// Replacement of the free variables and Skolem terms of a closed tableau by terms of the sequent
+/// and eigenvariables
+struct Grounding<'a> {
+    subst: Substitution,
+    skolems: Vec<String>,
+    names: Vec<(String, String)>,   // Name given to each Skolem function and unassigned variable
+    reserved: Vec<String>,          // Names given so far
+    used: &'a dyn Fn(&str) -> bool
+}
+
+impl Grounding<'_> {
+    /// Return the primitive rules proving falsum on the branch of the tableau
+    fn rules(&mut self, tableau: &Tableau) -> Vec<Rule> {
+        match tableau {
+            Tableau::Close(Formula::Falsum, _) => vec![Rule::Axiom],
+            Tableau::Close(_, negative) => vec![Rule::ExFalso(Box::new(self.formula(negative))), Rule::Axiom, Rule::Axiom],
+
+            Tableau::Alpha(f, components, t) => {
+                let f = self.formula(f);
+                let mut res = vec![];
+                for c in components {
+                    let c = self.formula(c);
+                    res.push(Rule::Cut(None, Box::new(c.clone())));
+                    res.extend(glue(&f, &c));
+                }
+                res.extend(self.rules(t));
+                res
+            }
+
+            Tableau::Beta(f, a, b, l, r) => {
+                let or = Formula::Or(Box::new(self.formula(a)), Box::new(self.formula(b)));
+                let mut res = vec![Rule::FromOr(Box::new(or.clone()))];
+                res.extend(glue(&self.formula(f), &or));
+                res.extend(self.rules(l));
+                res.extend(self.rules(r));
+                res
+            }
+
+            Tableau::Gamma(f, t, instance, child) => {
+                let (f, t, instance) = (self.formula(f), self.term(t), self.formula(instance));
+                let mut res = vec![Rule::Cut(None, Box::new(instance.clone()))];
+                res.extend(self.instance(&f, &t, &instance));
+                res.extend(self.rules(child));
+                res
+            }
+
+            Tableau::Delta(f, symbol, child) => {
+                let (f, c) = (self.formula(f), self.name(symbol));
+                let mut res = self.witness(&f, &c);
+                res.extend(self.rules(child));
+                res
+            }
+        }
+    }
+
+
+    /// Prove the instance of the universal formula `f` with the term `t`
+    fn instance(&mut self, f: &Formula, t: &Term, instance: &Formula) -> Vec<Rule> {
+        match f {
+            // ~F[t] from ~(exists x, F): F[t] is a witness of exists x, F
+            Formula::Not(_) => vec![
+                Rule::FromBottom,
+                Rule::ExFalso(Box::new(f.clone())),
+                Rule::FixAs(Box::new(t.clone())),
+                Rule::Axiom,
+                Rule::Axiom
+            ],
+
+            // F[t] from forall x, F: by contradiction, ~F[y] for some y, so F[y] cannot be generalized
+            Formula::Forall(x, body) => {
+                let y = self.fresh(x);
+                let counterexample = Formula::Exists(y.clone(), Box::new(Formula::Not(Box::new(body.substitute(x, &Term(y.clone(), vec![]))))));
+                let negated = match counterexample.clone() {
+                    Formula::Exists(_, g) => *g,
+                    _ => unreachable!()
+                };
+                let complement = match instance {
+                    Formula::Not(e) => *e.clone(),
+                    e => Formula::Not(Box::new(e.clone()))
+                };
+
+                [
+                    vec![Rule::FromBottom, Rule::Cut(None, Box::new(counterexample.clone())), Rule::FixAs(Box::new(t.clone()))],
+                    glue(&complement, &Formula::Not(Box::new(instance.clone()))),
+                    vec![
+                        Rule::Consider(Box::new(counterexample)),
+                        Rule::Axiom,
+                        Rule::ExFalso(Box::new(negated)),
+                        Rule::Generalize(Box::new(Term(y, vec![]))),
+                        Rule::Axiom,
+                        Rule::Axiom
+                    ]
+                ].concat()
+            }
+
+            _ => unreachable!()
+        }
+    }
+
+
+    /// Add the instance of the existential formula `f` (or the negation of a universal formula)
+    /// to the antecedents, with its eigenvariable
+    fn witness(&mut self, f: &Formula, c: &str) -> Vec<Rule> {
+        match f {
+            Formula::Exists(x, body) => {
+                let exists = Formula::Exists(c.to_string(), Box::new(body.substitute(x, &Term(c.to_string(), vec![]))));
+                vec![Rule::Consider(Box::new(exists)), Rule::Axiom]
+            }
+
+            // exists c, ~F[c] from ~(forall x, F), proven by contradiction
+            Formula::Not(g) => {
+                let (x, body) = match g.as_ref() {
+                    Formula::Forall(x, body) => (x, body),
+                    _ => unreachable!()
+                };
+                let c_term = Term(c.to_string(), vec![]);
+                let exists = Formula::Exists(c.to_string(), Box::new(Formula::Not(Box::new(body.substitute(x, &c_term)))));
+
+                let generic = body.substitute(x, &c_term);
+                let complement = match &generic {
+                    Formula::Not(e) => *e.clone(),
+                    e => Formula::Not(Box::new(e.clone()))
+                };
+
+                [
+                    vec![
+                        Rule::Cut(None, Box::new(exists.clone())),
+                        Rule::FromBottom,
+                        Rule::ExFalso(Box::new(f.clone())),
+                        Rule::RenameAs(c.to_string()),
+                        Rule::Intro,
+                        Rule::FromBottom,
+                        Rule::ExFalso(Box::new(Formula::Not(Box::new(exists.clone())))),
+                        Rule::FixAs(Box::new(c_term.clone()))
+                    ],
+                    glue(&complement, &Formula::Not(Box::new(generic))),
+                    vec![Rule::Axiom, Rule::Axiom, Rule::Consider(Box::new(exists)), Rule::Axiom]
+                ].concat()
+            }
+
+            _ => unreachable!()
+        }
+    }
+
+
+    /// Return the name replacing the Skolem function or the unassigned variable
+    fn name(&mut self, symbol: &str) -> String {
+        if let Some((_, n)) = self.names.iter().find(|(s, _)| s == symbol) {
+            return n.clone()
+        }
+
+        let base = match symbol.strip_prefix(SKOLEM_PREFIX) {
+            Some(i) => self.skolems[i.parse::<usize>().expect("Invalid Skolem function")].clone(),
+            None => "x".to_string()
+        };
+        let name = self.fresh(&base);
+        self.names.push((symbol.to_string(), name.clone()));
+        name
+    }
+
+    /// Return a variable name based on `base`, not used in the sequent nor by another name
+    fn fresh(&mut self, base: &str) -> String {
+        let name = (0..).map(|p| format!("{base}{}", "'".repeat(p)))
+            .find(|n| !(self.used)(n) && !self.reserved.contains(n))
+            .expect("Infinite iterator");
+        self.reserved.push(name.clone());
+        name
+    }
+
+
+    fn term(&mut self, t: &Term) -> Term {
+        let t = self.subst.apply_term(t);
+        if t.0.starts_with(SKOLEM_PREFIX) || t.0.starts_with(VARIABLE_PREFIX) {
+            return Term(self.name(&t.0), vec![])
+        }
+        Term(t.0.clone(), t.1.iter().map(|u| self.term(u)).collect())
+    }
+
+    /// Return the formula with the free variables and Skolem terms replaced
+    fn formula(&mut self, f: &Formula) -> Formula {
+        let mut res = f.clone();
+        for v in f.domain().iter().filter(|v| v.starts_with(VARIABLE_PREFIX)) {
+            let t = self.term(&Term(v.clone(), vec![]));
+            res = res.substitute(v, &t);
+        }
+        self.skolem_free(&res)
+    }
+
+    fn skolem_free(&mut self, f: &Formula) -> Formula {
+        match f {
+            Formula::Falsum => Formula::Falsum,
+            Formula::Relation(r, terms) => Formula::Relation(r.clone(), terms.iter().map(|t| self.term(t)).collect()),
+            Formula::Not(g) => Formula::Not(Box::new(self.skolem_free(g))),
+            Formula::Or(g, h) => Formula::Or(Box::new(self.skolem_free(g)), Box::new(self.skolem_free(h))),
+            Formula::And(g, h) => Formula::And(Box::new(self.skolem_free(g)), Box::new(self.skolem_free(h))),
+            Formula::Implies(g, h) => Formula::Implies(Box::new(self.skolem_free(g)), Box::new(self.skolem_free(h))),
+            Formula::Forall(x, g) => Formula::Forall(x.clone(), Box::new(self.skolem_free(g))),
+            Formula::Exists(x, g) => Formula::Exists(x.clone(), Box::new(self.skolem_free(g))),
+        }
+    }
+}
+
+
+/// Propositional proof of `to` from `from`
+fn glue(from: &Formula, to: &Formula) -> Vec<Rule> {
+    let antecedents = [from.clone()];
+    g4ip::prove(&antecedents, to)
+        .or_else(|| g4ip::prove_classical(&antecedents, to))
+        .expect("Invalid tableau expansion")
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::kernel;
+    use crate::proof::Proof;
+    use crate::syntax::lexer::Lexer;
+    use crate::tactics::firstorder;
+
+    /// Prove the formula with `firstorder`, and check the derivation with the kernel
+    fn firstorder_str(goal: &str, bound: usize) -> Result<(), Error> {
+        let goal = Formula::parse(&mut Lexer::from(goal)).expect("valid formula");
+        let mut proof = Proof::start(Box::new(goal));
+        firstorder(&mut proof, bound, &|_| false)?;
+        assert!(proof.is_finished());
+        kernel::check(&proof, &HashMap::new())
+    }
+
+    #[test]
+    fn valid_formulas() {
+        let valid = [
+            "exists x, (D(x) => (forall y, D(y)))",
+            "(exists y, forall x, R(x, y)) => (forall x, exists y, R(x, y))",
+            "(forall x, P(x) => Q(x)) => (forall x, P(x)) => (forall x, Q(x))",
+            "(forall x, P(x)) \\/ (forall x, Q(x)) => (forall x, P(x) \\/ Q(x))",
+            "~(exists x, P(x)) => (forall x, ~P(x))",
+            "(forall x, R(x, f(x))) => (forall x, exists y, R(x, y))",
+            "(forall x, forall y, R(x, y) => R(y, x)) /\\ R(a, b) => (exists x, R(b, x))"
+        ];
+        for f in valid {
+            let res = firstorder_str(f, DEFAULT_BOUND);
+            assert!(res.is_ok(), "{f}: {res:?}");
+        }
+    }
+
+    #[test]
+    fn converse_is_refused() {
+        let res = firstorder_str("(forall x, exists y, R(x, y)) => (exists y, forall x, R(x, y))", DEFAULT_BOUND);
+        let msg = res.expect_err("the converse is not valid").to_string();
+        assert_eq!(msg, "No proof found with at most 3 instances of each universal formula on a branch");
+
+        let res = firstorder_str("(forall x, P(x) \\/ Q(x)) => (forall x, P(x)) \\/ (forall x, Q(x))", 1);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn bound_is_needed() {
+        // P(f(f(a))) needs two instances of the universal formula
+        let f = "(forall x, P(x) => P(f(x))) /\\ P(a) => P(f(f(a)))";
+        assert!(firstorder_str(f, 1).is_err());
+        assert!(firstorder_str(f, 2).is_ok());
+    }
+
+    #[test]
+    fn steps_are_bounded() {
+        let formula = Formula::parse(&mut Lexer::from("~((forall x, P(x) \\/ Q(x)) => (forall x, P(x)) \\/ (forall x, Q(x)))")).unwrap();
+        match refute_within(&[formula], 20, &|_| false, 1000) {
+            Err(e) => assert_eq!(e.to_string(), "No refutation found in 1000 steps"),
+            r => panic!("expected the search to stop, got {r:?}")
+        }
+    }
+}
@@ -0,0 +1,589 @@
+//! Reading and writing of problems in the TPTP FOF syntax.
+//!
+//! A TPTP file is read as a list of engine commands: every axiom-like formula becomes an
+//! `Axiom` command, and the conjecture (if any) becomes a `Thm` command, run last.
+//!
+//! Formula names live in their own namespace in TPTP: a name which is also a symbol of the
+//! problem is suffixed with a number.
+//!
+//! TPTP variables (upper case) are kept as is. Sculpt variables are written with an upper case
+//! initial when bound, and as constants when free.
+//! Equality, types and `include` directives are not supported.
+
+use std::fmt::{Display, Formatter};
+use crate::engine::{ContextCommand, EngineCommand};
+use crate::error::Error;
+use crate::logic::{Formula, Term};
+use crate::syntax::lexer::is_ident_allowed;
+
+
+/// Role of a formula in a TPTP problem, as written by [write].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Axiom,
+    Theorem,
+    Conjecture
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Axiom => write!(f, "axiom"),
+            Role::Theorem => write!(f, "theorem"),
+            Role::Conjecture => write!(f, "conjecture")
+        }
+    }
+}
+
+
+/// Roles read as axioms
+const AXIOM_ROLES: [&str; 7] = ["axiom", "hypothesis", "definition", "assumption", "lemma", "theorem", "corollary"];
+
+/// Symbols, longest first
+const PUNCTUATION: [&str; 20] = ["<=>", "<~>", "=>", "<=", "~|", "~&", "!=", "(", ")", "[", "]", ",", ".", ":", "!", "?", "~", "&", "|", "="];
+
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LowerWord(String),
+    UpperWord(String),
+    Quoted(String),
+    DollarWord(String),
+    Integer(String),
+    Punct(&'static str)
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::LowerWord(s) | Token::UpperWord(s) | Token::DollarWord(s) | Token::Integer(s) => write!(f, "{s}"),
+            Token::Quoted(s) => write!(f, "'{s}'"),
+            Token::Punct(s) => write!(f, "{s}")
+        }
+    }
+}
+
+
+
+/// Read a TPTP problem, and return the commands adding it to a context.
+pub fn read(content: &str) -> Result<Vec<EngineCommand>, Error> {
+    let mut parser = Parser { tokens: tokenize(content)?, pos: 0, symbols: vec![] };
+
+    let mut axioms = vec![];
+    let mut conjecture = None;
+
+    while let Some(token) = parser.next() {
+        match token {
+            Token::LowerWord(w) if w == "fof" => (),
+            Token::LowerWord(w) if w == "include" => return Err(parser.error("include directives are not supported")),
+            Token::LowerWord(w) if ["cnf", "tff", "tcf", "thf"].contains(&w.as_str()) => {
+                return Err(parser.error(&format!("only FOF formulas are supported, got '{w}'")))
+            }
+            t => return Err(parser.error(&format!("expected 'fof', got '{t}'")))
+        }
+
+        parser.expect("(")?;
+        let name = parser.name()?;
+        parser.expect(",")?;
+        let role = match parser.next() {
+            Some(Token::LowerWord(r)) => r,
+            _ => return Err(parser.error("expected a formula role"))
+        };
+        parser.expect(",")?;
+        let formula = Box::new(parser.formula(&[])?);
+        parser.skip_annotations()?;
+        parser.expect(")")?;
+        parser.expect(".")?;
+
+        if AXIOM_ROLES.contains(&role.as_str()) {
+            axioms.push((name, formula));
+        }
+        else if role == "conjecture" {
+            if conjecture.is_some() {
+                return Err(parser.error("only one conjecture is supported"))
+            }
+            conjecture = Some((name, formula));
+        }
+        else {
+            return Err(parser.error(&format!("unsupported role '{role}'")))
+        }
+    }
+
+    let mut taken = parser.symbols;
+    let mut rename = |name: String| {
+        let res = if taken.contains(&name) {
+            (1..).map(|i| format!("{name}_{i}")).find(|n| !taken.contains(n)).expect("Infinite iterator")
+        } else { name };
+        taken.push(res.clone());
+        res
+    };
+
+    let mut commands = axioms.into_iter()
+        .map(|(n, f)| EngineCommand::ContextCommand(ContextCommand::Axiom(rename(n), f)))
+        .collect::<Vec<_>>();
+    if let Some((n, f)) = conjecture {
+        commands.push(EngineCommand::ContextCommand(ContextCommand::Theorem(rename(n), f)));
+    }
+
+    Ok(commands)
+}
+
+
+/// Write formulas as a TPTP problem, in order.
+pub fn write(statements: &[(&str, Role, Formula)]) -> String {
+    statements.iter()
+        .map(|(name, role, f)| format!("fof({}, {role}, {}).\n", name_str(name), formula_str(f, &mut vec![])))
+        .collect()
+}
+
+
+
+fn tokenize(content: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut tokens = vec![];
+    let mut chars = content.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            line += 1;
+            chars.next();
+        }
+        else if c.is_whitespace() {
+            chars.next();
+        }
+        // Line comment
+        else if c == '%' {
+            while chars.next_if(|&c| c != '\n').is_some() {}
+        }
+        // Block comment
+        else if c == '/' {
+            chars.next();
+            if chars.next() != Some('*') {
+                return Err(Error::InvalidTptp(line, "unexpected '/'".to_string()))
+            }
+            let mut previous = ' ';
+            loop {
+                match chars.next() {
+                    None => return Err(Error::InvalidTptp(line, "unterminated comment".to_string())),
+                    Some('/') if previous == '*' => break,
+                    Some(c) => {
+                        if c == '\n' { line += 1 }
+                        previous = c;
+                    }
+                }
+            }
+        }
+        else if c == '\'' {
+            chars.next();
+            let mut word = String::new();
+            loop {
+                match chars.next() {
+                    None | Some('\n') => return Err(Error::InvalidTptp(line, "unterminated quoted name".to_string())),
+                    Some('\'') => break,
+                    Some('\\') => word.extend(chars.next()),
+                    Some(c) => word.push(c)
+                }
+            }
+            tokens.push((line, Token::Quoted(word)));
+        }
+        else if c == '"' {
+            return Err(Error::InvalidTptp(line, "distinct objects are not supported".to_string()))
+        }
+        else if c.is_ascii_alphanumeric() || c == '$' {
+            let mut word = c.to_string();
+            chars.next();
+            while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '_') {
+                word.push(c);
+            }
+
+            let token = match c {
+                '$' => Token::DollarWord(word),
+                c if c.is_ascii_digit() => Token::Integer(word),
+                c if c.is_ascii_uppercase() => Token::UpperWord(word),
+                _ => Token::LowerWord(word)
+            };
+            tokens.push((line, token));
+        }
+        else {
+            let rest = chars.clone().take(3).collect::<String>();
+            match PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+                Some(p) => {
+                    for _ in 0..p.len() { chars.next(); }
+                    tokens.push((line, Token::Punct(p)));
+                }
+                None => return Err(Error::InvalidTptp(line, format!("unexpected '{c}'")))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+
+
+/// Recursive descent parser of FOF formulas
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    symbols: Vec<String> // Relations & functions read so far
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let res = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        res
+    }
+
+    fn is_next(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), Error> {
+        match self.next() {
+            Some(Token::Punct(p)) if p == punct => Ok(()),
+            Some(t) => Err(self.error(&format!("expected '{punct}', got '{t}'"))),
+            None => Err(self.error(&format!("expected '{punct}'")))
+        }
+    }
+
+    /// Error located at the last token read
+    fn error(&self, msg: &str) -> Error {
+        let line = self.tokens.get(self.pos.saturating_sub(1))
+            .or(self.tokens.last())
+            .map_or(1, |(l, _)| *l);
+        Error::InvalidTptp(line, msg.to_string())
+    }
+
+
+    /// Name of an annotated formula
+    fn name(&mut self) -> Result<String, Error> {
+        match self.next() {
+            Some(Token::LowerWord(s) | Token::Integer(s)) => Ok(s),
+            Some(Token::Quoted(s)) => self.ident(s),
+            _ => Err(self.error("expected a formula name"))
+        }
+    }
+
+    /// Check that a quoted name can be used as an identifier
+    fn ident(&self, s: String) -> Result<String, Error> {
+        if !s.is_empty() && s.chars().all(is_ident_allowed) { Ok(s) }
+        else { Err(self.error(&format!("'{s}' cannot be used as a name"))) }
+    }
+
+    /// Skip the source & useful info of an annotated formula
+    fn skip_annotations(&mut self) -> Result<(), Error> {
+        let mut depth = 0;
+        while depth > 0 || !self.is_next(")") {
+            match self.next() {
+                Some(Token::Punct("(" | "[")) => depth += 1,
+                Some(Token::Punct(")" | "]")) => depth -= 1,
+                Some(_) => (),
+                None => return Err(self.error("unexpected end of file"))
+            }
+        }
+        Ok(())
+    }
+
+
+    fn formula(&mut self, bound: &[String]) -> Result<Formula, Error> {
+        let lhs = self.unitary_formula(bound)?;
+
+        let op = match self.peek() {
+            Some(Token::Punct(p)) => *p,
+            _ => return Ok(lhs)
+        };
+
+        let res = match op {
+            // Associative operators
+            "&" | "|" => {
+                let mut res = lhs;
+                while self.is_next(op) {
+                    self.next();
+                    let rhs = Box::new(self.unitary_formula(bound)?);
+                    res = if op == "&" { Formula::And(Box::new(res), rhs) } else { Formula::Or(Box::new(res), rhs) };
+                }
+                res
+            }
+            "=>" | "<=" | "<=>" | "<~>" | "~|" | "~&" => {
+                self.next();
+                let lhs = Box::new(lhs);
+                let rhs = Box::new(self.unitary_formula(bound)?);
+                let equiv = || Formula::And(
+                    Box::new(Formula::Implies(lhs.clone(), rhs.clone())),
+                    Box::new(Formula::Implies(rhs.clone(), lhs.clone()))
+                );
+                match op {
+                    "=>" => Formula::Implies(lhs.clone(), rhs.clone()),
+                    "<=" => Formula::Implies(rhs.clone(), lhs.clone()),
+                    "<=>" => equiv(),
+                    "<~>" => Formula::Not(Box::new(equiv())),
+                    "~|" => Formula::Not(Box::new(Formula::Or(lhs.clone(), rhs.clone()))),
+                    _ => Formula::Not(Box::new(Formula::And(lhs.clone(), rhs.clone())))
+                }
+            }
+            _ => return Ok(lhs)
+        };
+
+        Ok(res)
+    }
+
+
+    fn unitary_formula(&mut self, bound: &[String]) -> Result<Formula, Error> {
+        match self.next() {
+            Some(Token::Punct(q @ ("!" | "?"))) => {
+                self.expect("[")?;
+                let mut vars = vec![];
+                loop {
+                    match self.next() {
+                        Some(Token::UpperWord(v)) => vars.push(v),
+                        _ => return Err(self.error("expected a variable"))
+                    }
+                    if self.is_next(",") { self.next(); } else { break }
+                }
+                self.expect("]")?;
+                self.expect(":")?;
+
+                let mut new_bound = bound.to_vec();
+                new_bound.extend(vars.iter().cloned());
+                let body = self.unitary_formula(&new_bound)?;
+
+                Ok(vars.into_iter().rev().fold(body, |f, v| match q {
+                    "!" => Formula::Forall(v, Box::new(f)),
+                    _ => Formula::Exists(v, Box::new(f))
+                }))
+            }
+            Some(Token::Punct("~")) => Ok(Formula::Not(Box::new(self.unitary_formula(bound)?))),
+            Some(Token::Punct("(")) => {
+                let res = self.formula(bound)?;
+                self.expect(")")?;
+                Ok(res)
+            }
+            Some(Token::DollarWord(w)) if w == "$false" => Ok(Formula::Falsum),
+            Some(Token::DollarWord(w)) if w == "$true" => Ok(Formula::Not(Box::new(Formula::Falsum))),
+            Some(Token::LowerWord(n)) => self.atom(n, bound),
+            Some(Token::Quoted(n)) => {
+                let n = self.ident(n)?;
+                self.atom(n, bound)
+            }
+            Some(Token::UpperWord(_)) if self.is_next("=") || self.is_next("!=") => Err(self.error("equality is not supported")),
+            Some(t) => Err(self.error(&format!("expected a formula, got '{t}'"))),
+            None => Err(self.error("expected a formula"))
+        }
+    }
+
+
+    fn atom(&mut self, name: String, bound: &[String]) -> Result<Formula, Error> {
+        let args = self.arguments(bound)?;
+        if self.is_next("=") || self.is_next("!=") {
+            return Err(self.error("equality is not supported"))
+        }
+        self.symbols.push(name.clone());
+        Ok(Formula::Relation(name, args))
+    }
+
+
+    fn arguments(&mut self, bound: &[String]) -> Result<Vec<Term>, Error> {
+        let mut args = vec![];
+        if self.is_next("(") {
+            self.next();
+            loop {
+                args.push(self.term(bound)?);
+                if self.is_next(",") { self.next(); } else { break }
+            }
+            self.expect(")")?;
+        }
+        Ok(args)
+    }
+
+
+    fn term(&mut self, bound: &[String]) -> Result<Term, Error> {
+        match self.next() {
+            Some(Token::UpperWord(v)) if bound.contains(&v) => Ok(Term(v, vec![])),
+            Some(Token::UpperWord(v)) => Err(self.error(&format!("variable '{v}' is not bound"))),
+            Some(Token::LowerWord(n) | Token::Integer(n)) => self.function(n, bound),
+            Some(Token::Quoted(n)) => {
+                let n = self.ident(n)?;
+                self.function(n, bound)
+            }
+            Some(t) => Err(self.error(&format!("expected a term, got '{t}'"))),
+            None => Err(self.error("expected a term"))
+        }
+    }
+
+
+    fn function(&mut self, name: String, bound: &[String]) -> Result<Term, Error> {
+        let args = self.arguments(bound)?;
+        self.symbols.push(name.clone());
+        Ok(Term(name, args))
+    }
+}
+
+
+
+/// Whether the string is a TPTP lower word
+fn is_lower_word(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_lowercase()) && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+
+/// Name of an annotated formula
+fn name_str(name: &str) -> String {
+    if is_lower_word(name) || name.chars().all(|c| c.is_ascii_digit()) { name.to_string() }
+    else { quote(name) }
+}
+
+
+/// Name of a relation or of a function
+fn functor_str(name: &str) -> String {
+    if is_lower_word(name) { name.to_string() }
+    else { quote(name) }
+}
+
+
+/// Return a TPTP variable name for the bound variable `v`, different from the variables in scope
+fn variable_str(v: &str, bound: &[(String, String)]) -> String {
+    let base = match v.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+            format!("{}{}", c.to_ascii_uppercase(), &v[1..])
+        }
+        _ => "X".to_string()
+    };
+
+    let taken = |n: &String| bound.iter().any(|(_, t)| t == n);
+    if !taken(&base) { return base }
+
+    (1..).map(|i| format!("{base}{i}"))
+        .find(|n| !taken(n))
+        .expect("Infinite iterator")
+}
+
+
+/// `bound` maps the variables bound in the context to their TPTP name
+fn formula_str(f: &Formula, bound: &mut Vec<(String, String)>) -> String {
+    match f {
+        Formula::Falsum => "$false".to_string(),
+        Formula::Relation(n, args) => format!("{}{}", functor_str(n), arguments_str(args, bound)),
+        Formula::Not(f) => format!("~ {}", formula_str(f, bound)),
+        Formula::And(l, r) => format!("({} & {})", formula_str(l, bound), formula_str(r, bound)),
+        Formula::Or(l, r) => format!("({} | {})", formula_str(l, bound), formula_str(r, bound)),
+        Formula::Implies(l, r) => format!("({} => {})", formula_str(l, bound), formula_str(r, bound)),
+        Formula::Forall(v, body) | Formula::Exists(v, body) => {
+            let q = if let Formula::Forall(..) = f { "!" } else { "?" };
+            let name = variable_str(v, bound);
+            bound.push((v.clone(), name.clone()));
+            let body = formula_str(body, bound);
+            bound.pop();
+            format!("{q} [{name}] : {body}")
+        }
+    }
+}
+
+
+fn arguments_str(args: &[Term], bound: &mut Vec<(String, String)>) -> String {
+    if args.is_empty() { return String::new() }
+    let args = args.iter().map(|t| term_str(t, bound)).collect::<Vec<_>>();
+    format!("({})", args.join(", "))
+}
+
+
+fn term_str(t: &Term, bound: &mut Vec<(String, String)>) -> String {
+    match bound.iter().rev().find(|(v, _)| v == &t.0) {
+        Some((_, name)) if t.1.is_empty() => name.clone(),
+        _ => format!("{}{}", functor_str(&t.0), arguments_str(&t.1, bound))
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Statements added by the commands read from the problem
+    fn statements(content: &str) -> Vec<(String, Role, Formula)> {
+        read(content).expect("valid problem").into_iter()
+            .map(|c| match c {
+                EngineCommand::ContextCommand(ContextCommand::Axiom(n, f)) => (n, Role::Axiom, *f),
+                EngineCommand::ContextCommand(ContextCommand::Theorem(n, f)) => (n, Role::Conjecture, *f),
+                c => panic!("unexpected command {c}")
+            })
+            .collect()
+    }
+
+    /// Read the problem, write it back and read it again: both readings must be the same
+    fn round_trip(content: &str) -> String {
+        let first = statements(content);
+        let written = write(&first.iter().map(|(n, r, f)| (n.as_str(), *r, f.clone())).collect::<Vec<_>>());
+        assert_eq!(statements(&written), first, "{written}");
+        written
+    }
+
+    fn relation(name: &str, args: &[&str]) -> Formula {
+        Formula::Relation(name.to_string(), args.iter().map(|a| Term(a.to_string(), vec![])).collect())
+    }
+
+    #[test]
+    fn binders() {
+        let written = round_trip("fof(ax, axiom, ! [X,Y] : (p(X) => ? [Z] : q(X, Y, Z))).");
+        assert_eq!(written, "fof(ax, axiom, ! [X] : ! [Y] : (p(X) => ? [Z] : q(X, Y, Z))).\n");
+    }
+
+    #[test]
+    fn quoted_names() {
+        let written = round_trip("fof('Ax', axiom, 'P'('B', c)). fof('goal', conjecture, 'q').");
+        assert_eq!(written, "fof('Ax', axiom, 'P'('B', c)).\nfof(goal, conjecture, q).\n");
+
+        // Escaped quotes are read, but cannot be used in sculpt identifiers
+        let error = read("fof(a, axiom, 'it\\'s').").err().map(|e| e.to_string());
+        assert_eq!(error, Some("Invalid TPTP input (line 1): 'it's' cannot be used as a name".to_string()));
+    }
+
+    #[test]
+    fn equivalences() {
+        let equiv = Formula::And(
+            Box::new(Formula::Implies(Box::new(relation("p", &[])), Box::new(relation("q", &[])))),
+            Box::new(Formula::Implies(Box::new(relation("q", &[])), Box::new(relation("p", &[]))))
+        );
+        let read = statements("fof(a, axiom, p <=> q). fof(b, axiom, p <~> q).");
+        assert_eq!(read[0].2, equiv);
+        assert_eq!(read[1].2, Formula::Not(Box::new(equiv)));
+        round_trip("fof(a, axiom, p <=> q). fof(b, axiom, p <~> q).");
+    }
+
+    #[test]
+    fn connectives() {
+        round_trip("% comment\nfof(a, axiom, (p & q & r) | ~ s | $false). /* block\ncomment */ fof(b, axiom, p <= q). fof(c, axiom, p ~| q). fof(d, axiom, p ~& $true).");
+    }
+
+    #[test]
+    fn names_are_renamed() {
+        let read = statements("fof(p, axiom, p). fof(p_1, conjecture, p_1).");
+        assert_eq!(read[0].0, "p_2");
+        assert_eq!(read[1].0, "p_1_1");
+    }
+
+    #[test]
+    fn written_variables() {
+        let f = Formula::Forall("x".to_string(), Box::new(Formula::Forall("X".to_string(), Box::new(relation("R", &["x", "X", "y"])))));
+        assert_eq!(write(&[("t", Role::Theorem, f)]), "fof(t, theorem, ! [X] : ! [X1] : 'R'(X, X1, y)).\n");
+    }
+
+    #[test]
+    fn unsupported_input() {
+        let error = |content: &str| read(content).err().map(|e| e.to_string());
+        assert_eq!(error("fof(a, axiom, p(X))."), Some("Invalid TPTP input (line 1): variable 'X' is not bound".to_string()));
+        assert_eq!(error("fof(a, axiom,\n a = b)."), Some("Invalid TPTP input (line 2): equality is not supported".to_string()));
+        assert_eq!(error("cnf(a, axiom, p)."), Some("Invalid TPTP input (line 1): only FOF formulas are supported, got 'cnf'".to_string()));
+        assert_eq!(error("fof(a, conjecture, p). fof(b, conjecture, q)."), Some("Invalid TPTP input (line 1): only one conjecture is supported".to_string()));
+    }
+}
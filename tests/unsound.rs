@@ -0,0 +1,37 @@
+//! Regression suite of unsound proof scripts.
+//!
+//! Each script of `tests/unsound/` tries to prove a false theorem named `bad`, using a rule
+//! with an incorrect side condition. The engine must reject it with the expected error.
+//!
+//! The same scripts are run on the engine in `src/engine/engine.rs`, which also checks the
+//! command rejecting them: this suite checks the file interpreter.
+
+use std::process::Command;
+
+
+const SCRIPTS: [(&str, &str); 7] = [
+    ("rename_to_constant", "'a' is already defined in the context"),
+    ("intro_constant", "'x' is already defined in the context, use 'rename_as' first"),
+    ("intro_free_in_hypothesis", "y is free in the hypotheses, use 'rename_as' first"),
+    ("consider_free_in_goal", "y is free in the sequent"),
+    ("consider_free_in_hypothesis", "y is free in the sequent"),
+    ("rename_capture", "Not an axiom"),
+    ("gen_capture", "Not an axiom"),
+];
+
+
+#[test]
+fn unsound_scripts_are_rejected() {
+    for (script, expected) in SCRIPTS {
+        let path = format!("{}/tests/unsound/{script}.sculpt", env!("CARGO_MANIFEST_DIR"));
+        let output = Command::new(env!("CARGO_BIN_EXE_sculpt"))
+            .args(["exec", &path])
+            .output()
+            .expect("Unable to run sculpt");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(!output.status.success(), "{script}: the script was accepted\n{stdout}");
+        assert!(!stdout.contains("New theorem: bad"), "{script}: the false theorem was registered\n{stdout}");
+        assert!(stdout.lines().any(|l| l == format!("ERROR: {expected}")), "{script}: expected the error '{expected}'\n{stdout}");
+    }
+}